@@ -1,8 +1,29 @@
-use std::fmt::Write as FmtWrite;
+use std::{
+    collections::BTreeMap,
+    fmt::Write as FmtWrite,
+    fs,
+    ops::{Range, RangeInclusive},
+    path::{Path, PathBuf},
+    sync::mpsc::{Receiver, TryRecvError},
+    time::{Duration, Instant},
+};
 
-use ratatui::text::{Line, Text};
+use anyhow::{Context, Result, bail};
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span, Text},
+};
 
-/// Represents a single rendered row and its starting offset.
+use crate::{
+    args::{Base, MAX_BYTES_PER_ROW, MIN_BYTES_PER_ROW, PatchFormat},
+    io::{FileStamp, read_window, write_window},
+    patchfile,
+    source::{Chunk, Followed, Follower},
+    template::{Field, Template},
+    theme::{Rgb, Theme},
+};
+
+/// Represents a single rendered row and its starting offset within the loaded bytes.
 #[derive(Clone)]
 pub struct RowText {
     pub offset: usize,
@@ -14,62 +35,799 @@ pub struct RowText {
 pub struct App {
     file_name: String,
     bytes: Vec<u8>,
-    scroll_row: usize,
     bytes_per_row: usize,
+    /// One pane, or two when the view is split; `focus` is the one keys act on.
+    panes: Vec<Pane>,
+    focus: usize,
+    split: Option<Split>,
+    /// Scroll all panes together, keeping their distance.
+    scroll_lock: bool,
+    grouping: Grouping,
+    base: Base,
+    /// Recompute `bytes_per_row` whenever the frontend reports its width.
+    auto_width: bool,
+    theme: Option<Theme>,
+    incoming: Option<Receiver<Chunk>>,
+    follow: Option<Follower>,
+    message: Option<String>,
+    base_offset: usize,
+    relative_offsets: bool,
+    path: Option<PathBuf>,
+    read_only: bool,
+    /// Original values of bytes changed since the last save, keyed by index.
+    edits: BTreeMap<usize, u8>,
+    window_length: Option<usize>,
+    /// State of the file on disk when it was last loaded or saved.
+    stamp: Option<FileStamp>,
+    changed_on_disk: bool,
+    last_disk_check: Instant,
+    /// Destructive action the user has been warned about and must repeat to confirm.
+    pending: Option<Confirm>,
+    /// Index of the last search match, so searching again moves past it.
+    last_find: Option<usize>,
+    status_bar: bool,
+    /// Index of the byte under the cursor; may be past the end after the buffer shrinks.
+    cursor: usize,
+    /// Bytes picked with the mouse, as the index the drag started at and where it is now.
+    selection: Option<(usize, usize)>,
+    template: Option<Overlay>,
+}
+
+/// A template laid over the buffer and the fields it read.
+struct Overlay {
+    template: Template,
+    /// Index the template is applied at.
+    start: usize,
+    root: Field,
+    /// Ranges of the fields without children, for coloring bytes.
+    leaves: Vec<Range<usize>>,
+    /// The buffer changed since the fields were read.
+    stale: bool,
+}
+
+/// Scroll position of one view onto the buffer.
+#[derive(Clone, Copy)]
+struct Pane {
+    scroll_row: usize,
     view_rows: usize,
 }
 
+/// How two panes over the same buffer are laid out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Split {
+    /// One pane above the other.
+    Horizontal,
+    /// Panes side by side.
+    Vertical,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Confirm {
+    Reload,
+    Overwrite,
+}
+
 impl App {
     pub fn new(file_name: String, bytes: Vec<u8>, bytes_per_row: usize) -> Self {
         Self {
             file_name,
             bytes,
-            scroll_row: 0,
             bytes_per_row: bytes_per_row.max(1),
-            view_rows: 1,
+            panes: vec![Pane {
+                scroll_row: 0,
+                view_rows: 1,
+            }],
+            focus: 0,
+            split: None,
+            scroll_lock: false,
+            grouping: Grouping::default(),
+            base: Base::Hex,
+            auto_width: false,
+            theme: None,
+            incoming: None,
+            follow: None,
+            message: None,
+            base_offset: 0,
+            relative_offsets: false,
+            path: None,
+            read_only: false,
+            edits: BTreeMap::new(),
+            window_length: None,
+            stamp: None,
+            changed_on_disk: false,
+            last_disk_check: Instant::now(),
+            pending: None,
+            last_find: None,
+            status_bar: true,
+            cursor: 0,
+            selection: None,
+            template: None,
+        }
+    }
+
+    /// Set the file the buffer is saved back to. Without one the buffer cannot be saved.
+    pub fn set_path(&mut self, path: PathBuf) {
+        self.stamp = FileStamp::read(&path).ok();
+        self.path = Some(path);
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub fn is_modified(&self) -> bool {
+        !self.edits.is_empty()
+    }
+
+    pub fn byte_at(&self, index: usize) -> Option<u8> {
+        self.bytes.get(index).copied()
+    }
+
+    /// Overwrite one byte. Returns `false` (and sets a message) if editing is disabled.
+    pub fn set_byte(&mut self, index: usize, value: u8) -> bool {
+        if self.read_only {
+            self.message = Some("read-only: editing is disabled".to_string());
+            return false;
         }
+        let Some(slot) = self.bytes.get_mut(index) else {
+            return false;
+        };
+        self.pending = None;
+        let original = *self.edits.entry(index).or_insert(*slot);
+        *slot = value;
+        if value == original {
+            self.edits.remove(&index);
+        }
+        self.mark_template_stale();
+        true
+    }
+
+    /// Write the buffer back to its file, keeping a `.bak` copy of the previous contents.
+    ///
+    /// If the file changed on disk since it was loaded, the first call only warns; calling
+    /// again overwrites the other program's changes.
+    pub fn save(&mut self) -> Result<()> {
+        if self.read_only {
+            bail!("File is read-only");
+        }
+        let path = self.path.as_deref().context("No file to save to")?;
+        if self.changed_on_disk
+            && self.pending.replace(Confirm::Overwrite) != Some(Confirm::Overwrite)
+        {
+            self.message =
+                Some("file changed on disk; save again to overwrite, or press r to reload".into());
+            return Ok(());
+        }
+        let backup = write_window(path, self.base_offset, &self.bytes)?;
+        self.edits.clear();
+        self.stamp = FileStamp::read(path).ok();
+        self.changed_on_disk = false;
+        self.pending = None;
+        self.message = Some(match backup {
+            Some(backup) => format!("saved (backup: {})", backup.display()),
+            None => "saved".to_string(),
+        });
+        self.restart_follow();
+        Ok(())
+    }
+
+    /// Write the unsaved edits as a patch file, in the format its extension names.
+    /// Returns the number of changed bytes.
+    pub fn export_patch(&self, path: &Path) -> Result<usize> {
+        if self.base_offset > 0 || self.window_length.is_some() {
+            bail!("Export needs the whole file loaded (no --offset or --length)");
+        }
+        let mut original = self.bytes.clone();
+        for (&index, &value) in &self.edits {
+            original[index] = value;
+        }
+        let data = patchfile::encode(patchfile::format_for_path(path), &original, &self.bytes)?;
+        fs::write(path, data)
+            .with_context(|| format!("Failed to write patch file: {}", path.display()))?;
+        Ok(self.edits.len())
+    }
+
+    /// Apply a patch file to the buffer as unsaved edits, after checking that the buffer
+    /// holds the bytes the patch was made from. Returns the number of changed bytes and
+    /// the patch format.
+    pub fn apply_patch(&mut self, path: &Path) -> Result<(usize, PatchFormat)> {
+        if self.read_only {
+            bail!("File is read-only");
+        }
+        if self.base_offset > 0 || self.window_length.is_some() {
+            bail!("Applying a patch needs the whole file loaded (no --offset or --length)");
+        }
+        let data = fs::read(path)
+            .with_context(|| format!("Failed to read patch file: {}", path.display()))?;
+        let patch = patchfile::decode(&data)
+            .with_context(|| format!("Failed to parse patch file: {}", path.display()))?;
+        let patched = patch.apply(&self.bytes)?;
+        if patched.len() != self.bytes.len() {
+            bail!("The patch changes the file size; use the `apply` subcommand instead");
+        }
+        let mut changed = 0;
+        for (index, byte) in patched.into_iter().enumerate() {
+            if self.bytes[index] != byte {
+                self.set_byte(index, byte);
+                changed += 1;
+            }
+        }
+        Ok((changed, patch.format()))
+    }
+
+    /// Title for windows and panes, marking read-only and modified buffers.
+    pub fn title(&self) -> String {
+        format!(
+            "{}{}{}{}",
+            self.file_name,
+            if self.is_read_only() { " [RO]" } else { "" },
+            if self.is_modified() { " [+]" } else { "" },
+            if self.changed_on_disk {
+                " [changed on disk]"
+            } else {
+                ""
+            }
+        )
+    }
+
+    pub fn is_changed_on_disk(&self) -> bool {
+        self.changed_on_disk
+    }
+
+    /// Forget a pending "press again" confirmation, because the user pressed another key.
+    pub fn cancel_confirm(&mut self) {
+        self.pending = None;
+    }
+
+    /// Reload the file from disk, asking for confirmation first if that drops unsaved edits.
+    pub fn request_reload(&mut self) {
+        if self.path.is_none() {
+            self.message = Some("nothing to reload".to_string());
+            return;
+        }
+        if self.is_modified() && self.pending.replace(Confirm::Reload) != Some(Confirm::Reload) {
+            self.message = Some(format!(
+                "{} unsaved edit(s) will be lost; press r again to reload",
+                self.edits.len()
+            ));
+            return;
+        }
+        self.pending = None;
+        if let Err(err) = self.reload() {
+            self.message = Some(format!("reload failed: {err:#}"));
+        }
+    }
+
+    fn reload(&mut self) -> Result<()> {
+        let path = self.path.as_deref().context("No file to reload")?;
+        let stamp = FileStamp::read(path).ok();
+        self.bytes = read_window(path, self.base_offset, self.window_length)?;
+        self.stamp = stamp;
+        self.edits.clear();
+        self.changed_on_disk = false;
+        self.mark_template_stale();
+        let last_row = self.total_rows().saturating_sub(1);
+        for pane in &mut self.panes {
+            pane.scroll_row = pane.scroll_row.min(last_row);
+        }
+        self.message = Some("reloaded from disk".to_string());
+        self.restart_follow();
+        Ok(())
+    }
+
+    /// Point the follower at the file now on disk, continuing after the loaded bytes.
+    fn restart_follow(&mut self) {
+        let end = (self.base_offset + self.bytes.len()) as u64;
+        if let Some(follower) = &mut self.follow
+            && let Err(err) = follower.restart(end)
+        {
+            self.message = Some(format!("follow stopped: {err}"));
+            self.follow = None;
+        }
+    }
+
+    /// Notice another program rewriting the file. Followed files are expected to change.
+    fn check_disk(&mut self) -> bool {
+        if self.changed_on_disk
+            || self.follow.is_some()
+            || self.last_disk_check.elapsed() < DISK_CHECK_INTERVAL
+        {
+            return false;
+        }
+        self.last_disk_check = Instant::now();
+        let (Some(path), Some(stamp)) = (&self.path, &self.stamp) else {
+            return false;
+        };
+        if FileStamp::read(path).ok().as_ref() == Some(stamp) {
+            return false;
+        }
+        self.changed_on_disk = true;
+        self.message = Some(if self.is_modified() {
+            "file changed on disk and conflicts with unsaved edits; press r to reload".to_string()
+        } else {
+            "file changed on disk; press r to reload".to_string()
+        });
+        true
+    }
+
+    /// Declare that the loaded bytes start at `base_offset` in the underlying file and
+    /// cover `length` bytes (or up to end of file), which is what a reload reads back.
+    ///
+    /// Offsets are displayed as absolute file offsets unless `relative_offsets` is set.
+    pub fn set_window(
+        &mut self,
+        base_offset: usize,
+        length: Option<usize>,
+        relative_offsets: bool,
+    ) {
+        self.base_offset = base_offset;
+        self.window_length = length;
+        self.relative_offsets = relative_offsets;
+    }
+
+    /// Offset shown to the user for an index into the loaded bytes.
+    pub fn display_offset(&self, index: usize) -> usize {
+        if self.relative_offsets {
+            index
+        } else {
+            self.base_offset + index
+        }
+    }
+
+    /// Index into the loaded bytes for an offset as shown to the user.
+    pub fn index_of_offset(&self, offset: usize) -> Option<usize> {
+        let index = if self.relative_offsets {
+            offset
+        } else {
+            offset.checked_sub(self.base_offset)?
+        };
+        (index < self.bytes.len()).then_some(index)
+    }
+
+    /// Append data from a background reader as it arrives (see `poll_incoming`).
+    pub fn attach_stream(&mut self, incoming: Receiver<Chunk>) {
+        self.incoming = Some(incoming);
+    }
+
+    pub fn is_streaming(&self) -> bool {
+        self.incoming.is_some()
+    }
+
+    /// Keep appending data written to the file after it was loaded (`tail -f`).
+    pub fn set_follow(&mut self, follower: Follower) {
+        self.follow = Some(follower);
+    }
+
+    pub fn is_following(&self) -> bool {
+        self.follow.is_some()
+    }
+
+    /// Drain pending stream data, poll a followed file and check the file on disk for
+    /// outside changes, without blocking. Returns whether anything changed.
+    pub fn poll_incoming(&mut self) -> bool {
+        let mut changed = self.poll_follow() | self.check_disk() | self.refresh_template();
+        let Some(incoming) = &self.incoming else {
+            return changed;
+        };
+        loop {
+            match incoming.try_recv() {
+                Ok(Chunk::Data(data)) => {
+                    self.bytes.extend_from_slice(&data);
+                    if let Some(overlay) = &mut self.template {
+                        overlay.stale = true;
+                    }
+                    changed = true;
+                }
+                Ok(Chunk::Error(err)) => {
+                    self.message = Some(format!("read error: {err}"));
+                    self.incoming = None;
+                    return true;
+                }
+                Err(TryRecvError::Empty) => return changed,
+                Err(TryRecvError::Disconnected) => {
+                    self.incoming = None;
+                    return true;
+                }
+            }
+        }
+    }
+
+    /// Suffix describing live input, for status lines.
+    pub fn source_state(&self) -> &'static str {
+        if self.is_streaming() {
+            " (reading)"
+        } else if self.is_following() {
+            " (following)"
+        } else {
+            ""
+        }
+    }
+
+    fn poll_follow(&mut self) -> bool {
+        // A replaced file with unsaved edits waits for the user to reload.
+        if self.changed_on_disk {
+            return false;
+        }
+        let Some(follower) = &mut self.follow else {
+            return false;
+        };
+        match follower.poll() {
+            Ok(Followed::Appended(data)) if data.is_empty() => false,
+            Ok(Followed::Replaced) if self.is_modified() => {
+                self.changed_on_disk = true;
+                self.message = Some(
+                    "file replaced on disk and conflicts with unsaved edits; press r to reload"
+                        .to_string(),
+                );
+                true
+            }
+            Ok(Followed::Replaced) => {
+                if let Err(err) = self.reload() {
+                    self.message = Some(format!("follow stopped: {err:#}"));
+                    self.follow = None;
+                }
+                true
+            }
+            Ok(Followed::Appended(data)) => {
+                // Panes stay on the tail only if the user has not scrolled away from it.
+                let old_rows = self.total_rows();
+                self.bytes.extend_from_slice(&data);
+                self.mark_template_stale();
+                let new_rows = self.total_rows();
+                for pane in &mut self.panes {
+                    if pane.scroll_row + pane.view_rows >= old_rows {
+                        pane.scroll_row = new_rows.saturating_sub(pane.view_rows);
+                    }
+                }
+                // A cursor the focused pane scrolled past moves to the same column of the
+                // new last row, so it stays on screen.
+                let row = self.cursor / self.bytes_per_row;
+                if row < self.panes[self.focus].scroll_row {
+                    let column = self.cursor % self.bytes_per_row;
+                    let last_row = new_rows.saturating_sub(1) * self.bytes_per_row;
+                    self.cursor = (last_row + column).min(self.bytes.len() - 1);
+                }
+                true
+            }
+            Err(err) => {
+                self.message = Some(format!("follow stopped: {err}"));
+                self.follow = None;
+                true
+            }
+        }
+    }
+
+    /// Read `template` from the buffer at `start` and show its fields over the bytes.
+    pub fn set_template(&mut self, template: Template, start: usize) -> Result<&Field> {
+        let root = template.apply(&self.bytes, start)?;
+        let overlay = self.template.insert(Overlay {
+            leaves: root.leaf_ranges(),
+            template,
+            start,
+            root,
+            stale: false,
+        });
+        Ok(&overlay.root)
+    }
+
+    pub fn clear_template(&mut self) {
+        self.template = None;
+    }
+
+    /// Fields of the applied template, if any.
+    pub fn template_fields(&self) -> Option<&Field> {
+        self.template.as_ref().map(|overlay| &overlay.root)
+    }
+
+    /// Theme background of the template field holding the byte at `index`. Neighbouring
+    /// fields alternate between two colors so their edges show.
+    pub fn field_color(&self, index: usize) -> Option<Rgb> {
+        let theme = self.theme.as_ref()?;
+        let leaves = &self.template.as_ref()?.leaves;
+        let position = leaves.partition_point(|range| range.end <= index);
+        if !leaves.get(position)?.contains(&index) {
+            return None;
+        }
+        Some(if position % 2 == 0 {
+            theme.field
+        } else {
+            theme.field_alt
+        })
+    }
+
+    /// Select the bytes of a template field and put the cursor on its first byte.
+    pub fn select_field(&mut self, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+        self.set_cursor(range.start);
+        self.select(range.start, range.end - 1);
+    }
+
+    fn mark_template_stale(&mut self) {
+        if let Some(overlay) = &mut self.template {
+            overlay.stale = true;
+        }
+    }
+
+    /// Read the template again after the buffer changed. If it no longer fits the data,
+    /// the old fields stay and the status line says why.
+    fn refresh_template(&mut self) -> bool {
+        let Some(overlay) = self.template.as_mut().filter(|overlay| overlay.stale) else {
+            return false;
+        };
+        overlay.stale = false;
+        match overlay.template.apply(&self.bytes, overlay.start) {
+            Ok(root) => {
+                overlay.leaves = root.leaf_ranges();
+                overlay.root = root;
+            }
+            Err(err) => self.message = Some(format!("template: {err:#}")),
+        }
+        true
+    }
+
+    /// Transient notice shown in the status line.
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    pub fn set_message(&mut self, message: String) {
+        self.message = Some(message);
     }
 
     pub fn file_name(&self) -> &str {
         &self.file_name
     }
 
-    pub fn set_view_rows(&mut self, rows: usize) {
-        self.view_rows = rows.max(1);
+    /// Split the view into two panes laid out as `split`, starting at the current row.
+    /// Asking for the layout that is already active closes the split again.
+    pub fn toggle_split(&mut self, split: Split) {
+        match self.split {
+            Some(current) if current == split => {
+                self.panes = vec![self.panes[self.focus]];
+                self.focus = 0;
+                self.split = None;
+            }
+            Some(_) => self.split = Some(split),
+            None => {
+                self.panes.push(self.panes[self.focus]);
+                self.split = Some(split);
+            }
+        }
+    }
+
+    pub fn split(&self) -> Option<Split> {
+        self.split
+    }
+
+    pub fn pane_count(&self) -> usize {
+        self.panes.len()
     }
 
+    pub fn focused_pane(&self) -> usize {
+        self.focus
+    }
+
+    pub fn focus_pane(&mut self, pane: usize) {
+        if pane < self.panes.len() {
+            self.focus = pane;
+        }
+    }
+
+    pub fn focus_next_pane(&mut self) {
+        self.focus = (self.focus + 1) % self.panes.len();
+    }
+
+    /// Select the bytes from `anchor` to `end`, in either order.
+    pub fn select(&mut self, anchor: usize, end: usize) {
+        let last = self.bytes.len().saturating_sub(1);
+        self.selection = Some((anchor.min(last), end.min(last)));
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.selection = None;
+    }
+
+    /// Selected byte indexes, lowest first.
+    pub fn selection(&self) -> Option<RangeInclusive<usize>> {
+        let (anchor, end) = self.selection?;
+        Some(anchor.min(end)..=anchor.max(end))
+    }
+
+    /// Byte shown at character `column` of line `line` in `pane`, if a hex or ASCII cell
+    /// is there.
+    pub fn index_at(&self, pane: usize, line: usize, column: usize) -> Option<usize> {
+        let offset = (self.panes.get(pane)?.scroll_row + line).checked_mul(self.bytes_per_row)?;
+        let chunk = self
+            .bytes
+            .get(offset..(offset + self.bytes_per_row).min(self.bytes.len()))?;
+        let mut position = 0;
+        let mut found = None;
+        for_each_piece(
+            self.display_offset(offset),
+            chunk,
+            self.bytes_per_row,
+            self.grouping,
+            self.base,
+            |text, piece| {
+                let end = position + text.chars().count();
+                if (position..end).contains(&column)
+                    && let Piece::Byte { column, .. } | Piece::Ascii { column, .. } = piece
+                {
+                    found = Some(offset + column);
+                }
+                position = end;
+            },
+        );
+        found
+    }
+
+    pub fn toggle_status_bar(&mut self) {
+        self.status_bar = !self.status_bar;
+    }
+
+    pub fn is_status_bar_visible(&self) -> bool {
+        self.status_bar
+    }
+
+    pub fn toggle_scroll_lock(&mut self) {
+        self.scroll_lock = !self.scroll_lock;
+    }
+
+    pub fn is_scroll_locked(&self) -> bool {
+        self.scroll_lock
+    }
+
+    pub fn set_view_rows(&mut self, pane: usize, rows: usize) {
+        if let Some(pane) = self.panes.get_mut(pane) {
+            pane.view_rows = rows.max(1);
+        }
+    }
+
+    /// Rows visible in the focused pane.
     pub fn view_rows(&self) -> usize {
-        self.view_rows
+        self.panes[self.focus].view_rows
+    }
+
+    /// Byte under the cursor, or `None` while the buffer is empty.
+    pub fn cursor(&self) -> Option<usize> {
+        (!self.bytes.is_empty()).then(|| self.cursor.min(self.bytes.len() - 1))
+    }
+
+    /// Move the cursor to byte `index`, scrolling the focused pane just enough to show it.
+    pub fn set_cursor(&mut self, index: usize) {
+        self.cursor = index.min(self.bytes.len().saturating_sub(1));
+        let row = self.cursor / self.bytes_per_row;
+        let Pane {
+            scroll_row,
+            view_rows,
+        } = self.panes[self.focus];
+        if row < scroll_row {
+            self.scroll_panes(row);
+        } else if row >= scroll_row + view_rows {
+            self.scroll_panes(row + 1 - view_rows);
+        }
+    }
+
+    /// Move the cursor `delta` bytes, stopping at either end of the buffer.
+    pub fn move_cursor(&mut self, delta: isize) {
+        let current = self.cursor().unwrap_or(0);
+        self.set_cursor(current.saturating_add_signed(delta));
+    }
+
+    /// Move the cursor to byte `index` and scroll its row to the top of the focused pane.
+    pub fn goto(&mut self, index: usize) {
+        self.scroll_panes(index / self.bytes_per_row);
+        self.cursor = index.min(self.bytes.len().saturating_sub(1));
     }
 
     pub fn scroll_rows(&mut self, delta: isize) {
-        if self.total_rows() == 0 {
-            self.scroll_row = 0;
+        let next = (self.scroll_row() as isize + delta).max(0) as usize;
+        self.scroll_to_row(next);
+    }
+
+    /// First row shown in the focused pane.
+    pub fn scroll_row(&self) -> usize {
+        self.panes[self.focus].scroll_row
+    }
+
+    /// Scroll the focused pane to `row`, taking the cursor along by as many rows so it
+    /// stays on screen.
+    pub fn scroll_to_row(&mut self, row: usize) {
+        let before = self.scroll_row();
+        self.scroll_panes(row);
+        let Some(cursor) = self.cursor() else {
             return;
+        };
+        let stride = self.bytes_per_row;
+        let top = self.scroll_row();
+        let bottom = top + self.view_rows() - 1;
+        let cursor_row = (cursor / stride + top)
+            .saturating_sub(before)
+            .clamp(top, bottom);
+        self.cursor = (cursor_row * stride + cursor % stride).min(self.bytes.len() - 1);
+    }
+
+    /// Scroll the focused pane to `row`. With scroll lock on, the other panes move by the
+    /// same number of rows.
+    fn scroll_panes(&mut self, row: usize) {
+        let last_row = self.total_rows().saturating_sub(1);
+        let current = self.scroll_row();
+        let next = row.min(last_row);
+        let delta = next as isize - current as isize;
+        for (idx, pane) in self.panes.iter_mut().enumerate() {
+            if idx == self.focus {
+                pane.scroll_row = next;
+            } else if self.scroll_lock {
+                pane.scroll_row =
+                    (pane.scroll_row as isize + delta).clamp(0, last_row as isize) as usize;
+            }
         }
-        let max_row = self.total_rows() - 1;
-        let next = (self.scroll_row as isize + delta).clamp(0, max_row as isize);
-        self.scroll_row = next as usize;
+    }
+
+    /// Find `pattern` from the cursor, or just after it if the cursor is still on the last
+    /// match, wrapping around at the end. Moves the cursor to the match.
+    pub fn find_next(&mut self, pattern: &[u8]) -> Option<usize> {
+        if pattern.is_empty() {
+            return None;
+        }
+        let cursor = self.cursor().unwrap_or(0);
+        let start = if self.last_find == Some(cursor) {
+            cursor + 1
+        } else {
+            cursor
+        };
+        let found = |from: usize| {
+            self.bytes
+                .get(from..)?
+                .windows(pattern.len())
+                .position(|window| window == pattern)
+                .map(|position| from + position)
+        };
+        let index = found(start).or_else(|| found(0))?;
+        self.last_find = Some(index);
+        self.goto(index);
+        Some(index)
+    }
+
+    /// Overwrite `count` bytes from `index` with `value`.
+    pub fn fill(&mut self, index: usize, count: usize, value: u8) -> Result<()> {
+        let end = index
+            .checked_add(count)
+            .filter(|end| *end <= self.bytes.len())
+            .context("Fill runs past the end of the buffer")?;
+        for index in index..end {
+            if !self.set_byte(index, value) {
+                bail!("File is read-only");
+            }
+        }
+        Ok(())
     }
 
     pub fn scroll_to_start(&mut self) {
-        self.scroll_row = 0;
+        self.goto(0);
     }
 
     pub fn scroll_to_end(&mut self) {
-        if self.total_rows() == 0 {
-            self.scroll_row = 0;
-        } else {
-            self.scroll_row = self.total_rows() - 1;
-        }
+        self.goto(self.bytes.len().saturating_sub(1));
     }
 
-    pub fn render_lines(&self, rows: usize) -> Text<'static> {
+    /// Text for `rows` rows of `pane`.
+    pub fn render_lines(&self, pane: usize, rows: usize) -> Text<'static> {
         if self.bytes.is_empty() {
-            return Text::from(vec![Line::from("File is empty.")]);
+            return Text::from(vec![Line::from(self.empty_text())]);
         }
 
-        let mut rows_with_text = self.lines_for_range(self.scroll_row, rows);
+        let mut rows_with_text = self.lines_for_range(self.panes[pane].scroll_row, rows);
         if rows_with_text.is_empty() {
             rows_with_text.push(RowText {
                 offset: 0,
@@ -80,11 +838,96 @@ impl App {
 
         let tui_lines: Vec<Line> = rows_with_text
             .into_iter()
-            .map(|row| Line::from(row.text))
+            .map(|row| {
+                let end = row.offset + row.bytes.len();
+                let selected = self.selection().is_some_and(|selection| {
+                    *selection.start() < end && *selection.end() >= row.offset
+                });
+                let has_cursor = self
+                    .cursor()
+                    .is_some_and(|cursor| (row.offset..end).contains(&cursor));
+                if row.bytes.is_empty() || (self.theme.is_none() && !selected && !has_cursor) {
+                    Line::from(row.text)
+                } else {
+                    self.styled_line(&row)
+                }
+            })
             .collect();
         Text::from(tui_lines)
     }
 
+    /// A row with every byte colored by its class, in both the byte and ASCII columns,
+    /// template fields shaded, and the cursor and selected bytes highlighted.
+    fn styled_line(&self, row: &RowText) -> Line<'static> {
+        let selection = self.selection();
+        let cursor = self.cursor();
+        let mut spans: Vec<Span<'static>> = Vec::new();
+        let mut current: Option<(Style, String)> = None;
+        for_each_piece(
+            self.display_offset(row.offset),
+            &row.bytes,
+            self.bytes_per_row,
+            self.grouping,
+            self.base,
+            |text, piece| {
+                let theme = self.theme.as_ref();
+                let mut style = Style::default();
+                match piece {
+                    Piece::Offset => {
+                        if let Some(theme) = theme {
+                            style = style.fg(theme.offset.into());
+                        }
+                    }
+                    Piece::Byte { column, byte } | Piece::Ascii { column, byte } => {
+                        let index = row.offset + column;
+                        if let Some(theme) = theme {
+                            style = style.fg(theme.bytes.color_of(byte).into());
+                            if let Some(color) = self.field_color(index) {
+                                style = style.bg(color.into());
+                            }
+                        }
+                        if cursor == Some(index) {
+                            style = match theme {
+                                Some(theme) => style.bg(theme.cursor.into()),
+                                None => style.add_modifier(Modifier::REVERSED),
+                            };
+                        } else if selection
+                            .as_ref()
+                            .is_some_and(|selection| selection.contains(&index))
+                        {
+                            style = match theme {
+                                Some(theme) => style.bg(theme.selection.into()),
+                                None => style.add_modifier(Modifier::UNDERLINED),
+                            };
+                        }
+                    }
+                    Piece::Filler => {}
+                }
+                match &mut current {
+                    Some((current_style, buf)) if *current_style == style => buf.push_str(text),
+                    _ => {
+                        if let Some((style, buf)) = current.replace((style, text.to_string())) {
+                            spans.push(Span::styled(buf, style));
+                        }
+                    }
+                }
+            },
+        );
+        if let Some((style, buf)) = current {
+            spans.push(Span::styled(buf, style));
+        }
+        Line::from(spans)
+    }
+
+    /// Colors to draw with, or `None` to draw plain text.
+    pub fn theme(&self) -> Option<&Theme> {
+        self.theme.as_ref()
+    }
+
+    pub fn set_theme(&mut self, theme: Option<Theme>) {
+        self.theme = theme;
+    }
+
     pub fn lines_for_range(&self, start_row: usize, rows: usize) -> Vec<RowText> {
         if self.bytes.is_empty() || rows == 0 {
             return Vec::new();
@@ -100,7 +943,13 @@ impl App {
             let chunk = &self.bytes[offset..end];
             lines.push(RowText {
                 offset,
-                text: format_line(offset, chunk, self.bytes_per_row),
+                text: format_line(
+                    self.display_offset(offset),
+                    chunk,
+                    self.bytes_per_row,
+                    self.grouping,
+                    self.base,
+                ),
                 bytes: chunk.to_vec(),
             });
         }
@@ -108,23 +957,60 @@ impl App {
         lines
     }
 
-    pub fn status_line(&self) -> String {
+    /// Placeholder shown instead of rows when there is no data.
+    pub fn empty_text(&self) -> &'static str {
+        if self.is_streaming() {
+            "Waiting for input..."
+        } else {
+            "File is empty."
+        }
+    }
+
+    /// One-line summary for the status bar, giving the cursor position; `quit_keys` is the
+    /// key hint for quitting.
+    pub fn status_line(&self, quit_keys: Option<&str>) -> String {
         let total_rows = self.total_rows().max(1);
-        format!(
-            "{} | bytes: {} | row: {}/{} | offset: 0x{:08X} | press q to quit",
-            self.file_name,
+        let cursor = self.cursor().unwrap_or(0);
+        let mut line = format!(
+            "{} | bytes: {}{} | row: {}/{} | offset: 0x{:08X}",
+            self.title(),
             self.bytes.len(),
-            self.scroll_row.saturating_add(1).min(total_rows),
+            self.source_state(),
+            cursor / self.bytes_per_row + 1,
             total_rows,
-            self.current_offset()
-        )
+            self.display_offset(cursor)
+        );
+        if let Some(selection) = self.selection() {
+            let _ = write!(
+                line,
+                " | selected: {} bytes from 0x{:08X}",
+                selection.end() - selection.start() + 1,
+                self.display_offset(*selection.start())
+            );
+        }
+        if let Some(keys) = quit_keys {
+            let _ = write!(line, " | press {keys} to quit");
+        }
+        if self.split.is_some() {
+            let _ = write!(
+                line,
+                " | pane {}/{}{}",
+                self.focus + 1,
+                self.panes.len(),
+                if self.scroll_lock { " (locked)" } else { "" }
+            );
+        }
+        if let Some(message) = &self.message {
+            let _ = write!(line, " | {message}");
+        }
+        line
     }
 
     pub fn total_rows(&self) -> usize {
         if self.bytes.is_empty() {
             0
         } else {
-            (self.bytes.len() + self.bytes_per_row - 1) / self.bytes_per_row
+            self.bytes.len().div_ceil(self.bytes_per_row)
         }
     }
 
@@ -132,47 +1018,311 @@ impl App {
         self.bytes.len()
     }
 
+    pub fn grouping(&self) -> Grouping {
+        self.grouping
+    }
+
+    pub fn set_grouping(&mut self, grouping: Grouping) {
+        self.grouping = grouping;
+    }
+
+    pub fn base(&self) -> Base {
+        self.base
+    }
+
+    pub fn set_base(&mut self, base: Base) {
+        self.base = base;
+    }
+
     pub fn bytes_per_row(&self) -> usize {
         self.bytes_per_row
     }
 
-    fn current_offset(&self) -> usize {
-        self.scroll_row * self.bytes_per_row
+    /// Change the row width, keeping the byte at the top of each pane in view and then
+    /// scrolling the focused pane as needed to show the cursor.
+    pub fn set_bytes_per_row(&mut self, bytes_per_row: usize) {
+        let bytes_per_row = bytes_per_row.max(1);
+        if bytes_per_row == self.bytes_per_row {
+            return;
+        }
+        for pane in &mut self.panes {
+            pane.scroll_row = pane.scroll_row * self.bytes_per_row / bytes_per_row;
+        }
+        self.bytes_per_row = bytes_per_row;
+        if let Some(cursor) = self.cursor() {
+            self.set_cursor(cursor);
+        }
+    }
+
+    pub fn set_auto_width(&mut self, auto_width: bool) {
+        self.auto_width = auto_width;
+    }
+
+    pub fn is_auto_width(&self) -> bool {
+        self.auto_width
+    }
+
+    /// With auto width on, use the widest row for which `fits(app, bytes_per_row)` holds,
+    /// rounded down to whole groups. Returns whether the width changed.
+    pub fn fit_bytes_per_row(&mut self, fits: impl Fn(&Self, usize) -> bool) -> bool {
+        if !self.auto_width {
+            return false;
+        }
+        // Keep single bytes in fours so offsets stay easy to read.
+        let step = self.base.grouping(self.grouping).size.max(4);
+        let narrowest = MIN_BYTES_PER_ROW.div_ceil(step);
+        let best = (narrowest..=MAX_BYTES_PER_ROW / step)
+            .map(|groups| groups * step)
+            .take_while(|&width| fits(self, width))
+            .last()
+            .unwrap_or(narrowest * step);
+        let changed = best != self.bytes_per_row;
+        self.set_bytes_per_row(best);
+        changed
+    }
+
+    /// Characters a formatted row takes up at `width` bytes per row.
+    pub fn row_chars(&self, width: usize) -> usize {
+        format_line(0, &[], width, self.grouping, self.base)
+            .chars()
+            .count()
     }
 }
 
-fn format_line(offset: usize, chunk: &[u8], width: usize) -> String {
-    let mut hex_buf = String::with_capacity(width * 3 + 8);
-    for idx in 0..width {
-        if idx == width / 2 {
-            hex_buf.push(' ');
+/// How often to look at the file on disk for changes made by other programs.
+pub const DISK_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Group sizes offered by `--group` and the runtime toggles.
+pub const GROUP_SIZES: [usize; 4] = [1, 2, 4, 8];
+
+/// How bytes are grouped in the hex column.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Grouping {
+    /// Bytes per group, one of `GROUP_SIZES`.
+    pub size: usize,
+    /// Print each group as a little-endian word, like `xxd -e`.
+    pub little_endian: bool,
+}
+
+impl Default for Grouping {
+    fn default() -> Self {
+        Self {
+            size: 1,
+            little_endian: false,
         }
-        if let Some(&byte) = chunk.get(idx) {
-            let _ = write!(hex_buf, "{:02X}", byte);
-        } else {
-            hex_buf.push_str("  ");
+    }
+}
+
+impl Grouping {
+    /// The next larger group size, wrapping back to single bytes.
+    pub fn next_size(self) -> Self {
+        let idx = GROUP_SIZES.iter().position(|&size| size == self.size);
+        let size = GROUP_SIZES[idx.map_or(0, |idx| (idx + 1) % GROUP_SIZES.len())];
+        Self { size, ..self }
+    }
+
+    /// Column indexes of a `width`-byte row, group by group, in display order.
+    ///
+    /// Little-endian groups list their bytes last to first, so a short group at the end
+    /// of a row is padded on the left as in `xxd -e`.
+    pub fn columns(self, width: usize) -> Vec<Vec<usize>> {
+        (0..width)
+            .step_by(self.size.max(1))
+            .map(|start| {
+                let mut group: Vec<usize> = (start..(start + self.size).min(width)).collect();
+                if self.little_endian {
+                    group.reverse();
+                }
+                group
+            })
+            .collect()
+    }
+
+    /// Whether a wider gap goes before group `index`. Single bytes keep the extra space
+    /// at the midpoint of the row; wider groups are separated evenly.
+    pub fn gap_before(self, index: usize, width: usize) -> bool {
+        self.size == 1 && index > 0 && index == width / 2
+    }
+}
+
+impl Base {
+    /// The next base in the runtime toggle order.
+    pub fn next(self) -> Self {
+        match self {
+            Base::Hex => Base::Binary,
+            Base::Binary => Base::Octal,
+            Base::Octal => Base::Decimal,
+            Base::Decimal => Base::Hex,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Base::Hex => "hex",
+            Base::Binary => "binary",
+            Base::Octal => "octal",
+            Base::Decimal => "decimal",
         }
-        if idx + 1 != width {
-            hex_buf.push(' ');
+    }
+
+    /// Characters one byte takes up.
+    pub fn cell_width(self) -> usize {
+        match self {
+            Base::Hex => 2,
+            Base::Binary => 8,
+            Base::Octal | Base::Decimal => 3,
         }
     }
 
-    let mut ascii_buf = String::with_capacity(width);
-    for idx in 0..width {
-        if let Some(&byte) = chunk.get(idx) {
-            ascii_buf.push(printable(byte));
-        } else {
-            ascii_buf.push(' ');
+    pub fn format_byte(self, byte: u8) -> String {
+        match self {
+            Base::Hex => format!("{byte:02X}"),
+            Base::Binary => format!("{byte:08b}"),
+            Base::Octal => format!("{byte:03o}"),
+            Base::Decimal => format!("{byte:03}"),
         }
     }
 
-    format!("{offset:08X}  {hex_buf}  |{ascii_buf}|")
+    /// Grouping to use in this base. Joined hex or binary digits read as one word, but
+    /// octal and decimal ones do not, so those always show single bytes.
+    pub fn grouping(self, grouping: Grouping) -> Grouping {
+        match self {
+            Base::Hex | Base::Binary => grouping,
+            Base::Octal | Base::Decimal => Grouping::default(),
+        }
+    }
 }
 
-fn printable(byte: u8) -> char {
+pub(crate) fn format_line(
+    offset: usize,
+    chunk: &[u8],
+    width: usize,
+    grouping: Grouping,
+    base: Base,
+) -> String {
+    let mut line = String::with_capacity(width * (base.cell_width() + 2) + 16);
+    for_each_piece(offset, chunk, width, grouping, base, |text, _| {
+        line.push_str(text)
+    });
+    line
+}
+
+/// What a piece of a formatted row shows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Piece {
+    Offset,
+    /// A byte in the hex (or other base) column, at `column` within the row.
+    Byte {
+        column: usize,
+        byte: u8,
+    },
+    /// A byte in the ASCII column, at `column` within the row.
+    Ascii {
+        column: usize,
+        byte: u8,
+    },
+    /// Separators and padding.
+    Filler,
+}
+
+/// Walk the text of a formatted row piece by piece, saying what each piece shows so
+/// frontends can style them.
+pub(crate) fn for_each_piece(
+    offset: usize,
+    chunk: &[u8],
+    width: usize,
+    grouping: Grouping,
+    base: Base,
+    mut piece: impl FnMut(&str, Piece),
+) {
+    let grouping = base.grouping(grouping);
+    let blank = " ".repeat(base.cell_width());
+    piece(&format!("{offset:08X}"), Piece::Offset);
+    piece("  ", Piece::Filler);
+    for (idx, group) in grouping.columns(width).iter().enumerate() {
+        if idx > 0 {
+            piece(" ", Piece::Filler);
+        }
+        if grouping.gap_before(idx, width) {
+            piece(" ", Piece::Filler);
+        }
+        for &column in group {
+            match chunk.get(column) {
+                Some(&byte) => piece(&base.format_byte(byte), Piece::Byte { column, byte }),
+                None => piece(&blank, Piece::Filler),
+            }
+        }
+    }
+
+    piece("  |", Piece::Filler);
+    let mut buf = [0u8; 4];
+    for idx in 0..width {
+        match chunk.get(idx) {
+            Some(&byte) => piece(
+                printable(byte).encode_utf8(&mut buf),
+                Piece::Ascii { column: idx, byte },
+            ),
+            None => piece(" ", Piece::Filler),
+        }
+    }
+    piece("|", Piece::Filler);
+}
+
+pub(crate) fn printable(byte: u8) -> char {
     if byte.is_ascii_graphic() || byte == b' ' {
         byte as char
     } else {
         '.'
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changing_the_width_keeps_the_cursor_on_screen() {
+        let mut app = App::new("test".to_string(), vec![0; 1024], 32);
+        app.set_view_rows(0, 4);
+        app.set_cursor(32 * 3 + 31);
+        assert_eq!(app.scroll_row(), 0);
+
+        app.set_bytes_per_row(8);
+        let row = app.cursor().unwrap() / app.bytes_per_row();
+        assert!((app.scroll_row()..app.scroll_row() + 4).contains(&row));
+    }
+
+    #[test]
+    fn following_moves_the_cursor_along_with_the_view() {
+        let dir = std::env::temp_dir().join(format!("rhv-app-follow-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("log.bin");
+        fs::write(&path, [0; 64]).unwrap();
+        let mut app = App::new("test".to_string(), vec![0; 64], 16);
+        app.set_view_rows(0, 4);
+        app.set_follow(Follower::open(&path, 64).unwrap());
+        app.set_cursor(17);
+
+        // The view was on the tail, so it follows the new rows and takes the cursor along.
+        fs::write(&path, [0; 104]).unwrap();
+        assert!(app.poll_incoming());
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(app.scroll_row(), 3);
+        assert_eq!(app.cursor(), Some(97));
+        let row = app.cursor().unwrap() / app.bytes_per_row();
+        assert!((app.scroll_row()..app.scroll_row() + 4).contains(&row));
+    }
+
+    #[test]
+    fn auto_width_stays_in_the_fixed_width_range() {
+        let mut app = App::new("test".to_string(), vec![0; 64], 16);
+        app.set_auto_width(true);
+        app.fit_bytes_per_row(|_, _| true);
+        assert_eq!(app.bytes_per_row(), MAX_BYTES_PER_ROW);
+        app.fit_bytes_per_row(|_, _| false);
+        assert_eq!(app.bytes_per_row(), MIN_BYTES_PER_ROW);
+        app.fit_bytes_per_row(|app, width| app.row_chars(width) <= 80);
+        assert_eq!(app.bytes_per_row(), 16);
+    }
+}
@@ -1,17 +1,151 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use clap::Parser;
+use anyhow::{Result, bail};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Deserialize;
+
+use crate::{
+    app::{GROUP_SIZES, Grouping},
+    config::Config,
+    dump::PLAIN_WIDTH,
+    parse::{parse_byte, parse_hex_bytes, parse_number},
+    theme::{PaletteOverrides, Theme, no_color_env},
+};
 
 /// Command line arguments for launching the hex viewer.
 #[derive(Parser, Debug)]
-#[command(author, version, about = "Simple TUI hex viewer")]
+#[command(
+    author,
+    version,
+    about = "Simple TUI hex viewer",
+    after_help = "A file named like a subcommand, such as `diff`, opens with `-- diff` or `./diff` \
+                  (or read-only with `view diff`).",
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 pub struct Args {
-    /// Path to the target file.
-    pub path: PathBuf,
+    #[command(subcommand)]
+    pub command: Option<Command>,
 
-    /// Bytes per row (8-32, default: 16).
-    #[arg(long = "width", short = 'w', default_value_t = 16, value_parser = clap::value_parser!(usize))]
-    pub bytes_per_row: usize,
+    /// Viewer options used when no subcommand is given.
+    #[command(flatten)]
+    pub view: ViewArgs,
+}
+
+impl Args {
+    /// Resolve the subcommand to run. Bare arguments open the viewer with editing
+    /// enabled; the `view` subcommand is read-only unless given `--writable`.
+    pub fn into_command(self) -> Command {
+        match self.command {
+            Some(Command::View(mut view)) => {
+                view.readonly = !view.writable;
+                Command::View(view)
+            }
+            Some(command) => command,
+            None => Command::View(self.view),
+        }
+    }
+}
+
+/// Subcommands; running without one opens the viewer with editing enabled.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Browse a file interactively, read-only unless given `--writable`.
+    View(ViewArgs),
+    /// Compare two files; exits with 0 if identical, 1 if different, 2 on error.
+    Diff(DiffArgs),
+    /// Apply one-shot edits to a file without starting the TUI.
+    Patch(PatchArgs),
+    /// Apply an IPS, BPS or text patch file after checking the original bytes.
+    Apply(ApplyArgs),
+    /// Print a hex dump of a file (or part of it) to stdout.
+    Dump(DumpArgs),
+    /// Turn a hex dump back into binary.
+    Undump(UndumpArgs),
+}
+
+/// Arguments for the interactive viewer.
+#[derive(clap::Args, Debug)]
+pub struct ViewArgs {
+    /// Files to open, one tab each; `-` or omitted reads from a pipe on stdin. Put `--`
+    /// before a file named like a subcommand.
+    pub paths: Vec<PathBuf>,
+
+    /// Bytes per row (8-32, default: 16), or `auto` to fit the terminal or window.
+    #[arg(long = "width", short = 'w', value_parser = parse_width)]
+    pub width: Option<Width>,
+
+    /// Only load data starting at this offset (decimal or 0x-prefixed hex).
+    #[arg(long = "offset", short = 'o', visible_alias = "skip", short_alias = 's', default_value = "0", value_parser = parse_number)]
+    pub offset: usize,
+
+    /// Only load this many bytes (default: to end of file).
+    #[arg(long = "length", short = 'l', value_parser = parse_number)]
+    pub length: Option<usize>,
+
+    /// Show offsets relative to `--offset` instead of absolute file offsets.
+    #[arg(long = "relative-offsets")]
+    pub relative_offsets: bool,
+
+    /// Bytes per group in the hex column (1, 2, 4 or 8, default: 1).
+    #[arg(long = "group", short = 'g', value_parser = parse_group_size)]
+    pub group: Option<usize>,
+
+    /// Show each group as a little-endian word, like `xxd -e`.
+    #[arg(long = "little-endian", short = 'e')]
+    pub little_endian: bool,
+
+    /// Number base for the byte column (default: hex).
+    #[arg(long = "base", short = 'b', value_enum)]
+    pub base: Option<Base>,
+
+    /// Color theme: dark, light, high-contrast, colorblind or one from the config file.
+    #[arg(long = "theme")]
+    pub theme: Option<String>,
+
+    /// Byte class colors on top of the theme, e.g. `null=darkgray,high=#ffaf00`.
+    /// Classes: null, ff, whitespace, printable, control, high.
+    #[arg(long = "palette", value_parser = PaletteOverrides::parse)]
+    pub palette: Option<PaletteOverrides>,
+
+    /// Draw bytes without colors (also set by the `NO_COLOR` environment variable).
+    #[arg(long = "no-color")]
+    pub no_color: bool,
+
+    /// Key bindings to start from before the config file's `[keys]` table.
+    #[arg(long = "keymap", value_enum)]
+    pub keymap: Option<KeymapPreset>,
+
+    /// Config file to use instead of `~/.config/rust-hex-viewer/config.toml`.
+    #[arg(long = "config")]
+    pub config: Option<PathBuf>,
+
+    /// Keep loading data appended to the file, like `tail -f`.
+    #[arg(long = "follow", short = 'f', conflicts_with = "length")]
+    pub follow: bool,
+
+    /// Disable editing (implied by `view`, stdin input and files that are not writable).
+    #[arg(long = "readonly", visible_alias = "read-only")]
+    pub readonly: bool,
+
+    /// Allow editing under `view`, which is read-only otherwise.
+    #[arg(long = "writable", conflicts_with = "readonly")]
+    pub writable: bool,
+
+    /// Template file describing the data's structure; its fields are listed beside the
+    /// bytes.
+    #[arg(long = "template")]
+    pub template: Option<PathBuf>,
+
+    /// Offset to apply the template at (default: the first loaded byte).
+    #[arg(long = "template-offset", requires = "template", value_parser = parse_number)]
+    pub template_offset: Option<usize>,
+
+    /// Leave the mouse to the terminal, so it can select text, instead of using it to
+    /// scroll and select bytes in the TUI.
+    #[arg(long = "no-mouse")]
+    pub no_mouse: bool,
 
     /// Launch in GUI mode.
     #[arg(long = "gui")]
@@ -22,9 +156,354 @@ pub struct Args {
     pub debug: bool,
 }
 
-impl Args {
+impl ViewArgs {
+    /// The files to open in order, with `None` standing for stdin.
+    pub fn sources(&self) -> Vec<Option<&Path>> {
+        if self.paths.is_empty() {
+            return vec![None];
+        }
+        self.paths
+            .iter()
+            .map(|path| Some(path.as_path()).filter(|path| *path != Path::new("-")))
+            .collect()
+    }
+
+    /// Fill in options not given on the command line from the config file.
+    pub fn apply_config(&mut self, config: &Config) {
+        self.width = self.width.or(config.width);
+        self.group = self.group.or(config.group);
+        self.little_endian |= config.little_endian;
+        self.no_mouse |= config.no_mouse;
+        self.base = self.base.or(config.base);
+        self.keymap = self.keymap.or(config.keymap);
+    }
+
+    pub fn width(&self) -> Width {
+        self.width.unwrap_or(Width::Fixed(16))
+    }
+
+    /// Clamp bytes per row to the supported range. Auto width starts from the default
+    /// until the frontend knows how much room there is.
+    pub fn clamped_bytes_per_row(&self) -> usize {
+        match self.width() {
+            Width::Fixed(bytes_per_row) => clamp_bytes_per_row(bytes_per_row),
+            Width::Auto => 16,
+        }
+    }
+
+    pub fn base(&self) -> Base {
+        self.base.unwrap_or_default()
+    }
+
+    /// The theme to draw with, or `None` when colors are turned off.
+    pub fn theme(&self, config: &Config) -> Result<Option<Theme>> {
+        if self.no_color || no_color_env() {
+            return Ok(None);
+        }
+        let mut theme = config.theme(self.theme.as_deref())?;
+        if let Some(overrides) = &self.palette {
+            theme.bytes.apply(overrides);
+        }
+        Ok(Some(theme))
+    }
+
+    pub fn grouping(&self) -> Grouping {
+        Grouping {
+            size: self.group.unwrap_or(1),
+            little_endian: self.little_endian,
+        }
+    }
+}
+
+/// Arguments for the `diff` subcommand.
+#[derive(clap::Args, Debug)]
+pub struct DiffArgs {
+    /// Original file.
+    pub left: PathBuf,
+
+    /// File to compare against the original.
+    pub right: PathBuf,
+
+    /// Output format.
+    #[arg(long = "format", short = 'f', value_enum, default_value_t = DiffFormat::Text)]
+    pub format: DiffFormat,
+
+    /// Align inserted/removed data instead of comparing byte for byte.
+    #[arg(long = "align")]
+    pub align: bool,
+
+    /// Bytes per row for the hex listing (8-32, default: 16).
+    #[arg(long = "width", short = 'w', default_value_t = 16, value_parser = clap::value_parser!(usize))]
+    pub bytes_per_row: usize,
+}
+
+impl DiffArgs {
+    /// Clamp bytes per row to the supported range.
+    pub fn clamped_bytes_per_row(&self) -> usize {
+        clamp_bytes_per_row(self.bytes_per_row)
+    }
+}
+
+/// Output formats for the `diff` subcommand.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiffFormat {
+    /// One line per differing range.
+    Text,
+    /// A single JSON document.
+    Json,
+    /// Unified-style hex listing of the differing rows.
+    Hex,
+}
+
+/// Arguments for the `patch` subcommand.
+///
+/// Each `--offset` is paired, in order, with the next `--write` or `--fill`;
+/// each `--fill` is paired, in order, with a `--count`.
+#[derive(clap::Args, Debug)]
+pub struct PatchArgs {
+    /// File to modify.
+    #[arg(long = "file", short = 'f')]
+    pub file: PathBuf,
+
+    /// Offset of an edit (decimal or 0x-prefixed hex).
+    #[arg(long = "offset", short = 'o', required = true, value_parser = parse_number)]
+    pub offsets: Vec<usize>,
+
+    /// Bytes to write, e.g. "DE AD BE EF".
+    #[arg(long = "write", value_parser = parse_hex_bytes)]
+    pub writes: Vec<Vec<u8>>,
+
+    /// Byte value to fill with: decimal (`255`) or hex with a `0x` prefix (`0xFF`).
+    #[arg(long = "fill", value_parser = parse_byte, requires = "counts")]
+    pub fills: Vec<u8>,
+
+    /// Number of bytes for the matching `--fill`.
+    #[arg(long = "count", value_parser = parse_number, requires = "fills")]
+    pub counts: Vec<usize>,
+
+    /// Show the affected rows before and after without writing.
+    #[arg(long = "dry-run", short = 'n')]
+    pub dry_run: bool,
+
+    /// Do not keep a `.bak` copy of the original file.
+    #[arg(long = "no-backup")]
+    pub no_backup: bool,
+
+    /// Write the edits to a patch file instead of modifying the file.
+    #[arg(long = "export", short = 'e', conflicts_with = "dry_run")]
+    pub export: Option<PathBuf>,
+
+    /// Patch file format (default: from the export file extension, else text).
+    #[arg(long = "export-format", value_enum, requires = "export")]
+    pub export_format: Option<PatchFormat>,
+
+    /// Bytes per row for the dry-run listing (8-32, default: 16).
+    #[arg(long = "width", short = 'w', default_value_t = 16, value_parser = clap::value_parser!(usize))]
+    pub bytes_per_row: usize,
+}
+
+impl PatchArgs {
     /// Clamp bytes per row to the supported range.
     pub fn clamped_bytes_per_row(&self) -> usize {
-        self.bytes_per_row.clamp(8, 32)
+        clamp_bytes_per_row(self.bytes_per_row)
+    }
+}
+
+/// Patch file formats for `patch --export` and `apply`.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PatchFormat {
+    /// International Patching System (up to 16 MiB, no original bytes).
+    Ips,
+    /// Beat patch format with source and target checksums.
+    Bps,
+    /// Text lines of the form `offset: old -> new`.
+    Text,
+}
+
+/// Arguments for the `apply` subcommand.
+#[derive(clap::Args, Debug)]
+pub struct ApplyArgs {
+    /// File to modify.
+    #[arg(long = "file", short = 'f')]
+    pub file: PathBuf,
+
+    /// Patch file to apply (format is detected from its contents).
+    pub patch: PathBuf,
+
+    /// Show the affected rows before and after without writing.
+    #[arg(long = "dry-run", short = 'n')]
+    pub dry_run: bool,
+
+    /// Do not keep a `.bak` copy of the original file.
+    #[arg(long = "no-backup")]
+    pub no_backup: bool,
+
+    /// Bytes per row for the dry-run listing (8-32, default: 16).
+    #[arg(long = "width", short = 'w', default_value_t = 16, value_parser = clap::value_parser!(usize))]
+    pub bytes_per_row: usize,
+}
+
+impl ApplyArgs {
+    /// Clamp bytes per row to the supported range.
+    pub fn clamped_bytes_per_row(&self) -> usize {
+        clamp_bytes_per_row(self.bytes_per_row)
+    }
+}
+
+/// Arguments for the `dump` subcommand.
+#[derive(clap::Args, Debug)]
+pub struct DumpArgs {
+    /// File to dump.
+    pub path: PathBuf,
+
+    /// Output style.
+    #[arg(long = "style", short = 's', value_enum, default_value_t = DumpStyle::Default)]
+    pub style: DumpStyle,
+
+    /// Start offset (decimal or 0x-prefixed hex).
+    #[arg(long = "offset", short = 'o', visible_alias = "skip", default_value = "0", value_parser = parse_number)]
+    pub offset: usize,
+
+    /// Number of bytes to dump (default: to end of file).
+    #[arg(long = "length", short = 'l', value_parser = parse_number)]
+    pub length: Option<usize>,
+
+    /// Bytes per line (8-32, default: 16; plain style: 1-256, default: 30).
+    #[arg(long = "width", short = 'w', value_parser = clap::value_parser!(usize))]
+    pub bytes_per_row: Option<usize>,
+}
+
+impl DumpArgs {
+    /// Bytes per line for the selected style, clamped to the supported range.
+    pub fn clamped_bytes_per_row(&self) -> usize {
+        match self.style {
+            DumpStyle::Plain => self.bytes_per_row.unwrap_or(PLAIN_WIDTH).clamp(1, 256),
+            _ => clamp_bytes_per_row(self.bytes_per_row.unwrap_or(16)),
+        }
+    }
+}
+
+/// Output styles for the `dump` subcommand.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DumpStyle {
+    /// The viewer's own row format.
+    Default,
+    /// `xxd` style with two-byte groups.
+    Xxd,
+    /// `hexdump -C` style, collapsing repeated rows.
+    #[value(alias = "hexdump")]
+    Canonical,
+    /// Continuous hex digits, like `xxd -p`.
+    Plain,
+}
+
+/// Bytes per row in the viewer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "WidthSetting")]
+pub enum Width {
+    /// Fit as many bytes as the terminal or window has room for.
+    Auto,
+    Fixed(usize),
+}
+
+/// `width` as written in the config file: a number or `"auto"`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum WidthSetting {
+    Bytes(usize),
+    Text(String),
+}
+
+impl TryFrom<WidthSetting> for Width {
+    type Error = String;
+
+    fn try_from(setting: WidthSetting) -> Result<Self, String> {
+        match setting {
+            WidthSetting::Bytes(bytes) => Ok(Width::Fixed(bytes)),
+            WidthSetting::Text(text) => parse_width(&text),
+        }
+    }
+}
+
+/// Number bases the viewer can show bytes in.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Base {
+    #[default]
+    #[value(alias = "hexadecimal")]
+    #[serde(alias = "hexadecimal")]
+    Hex,
+    #[value(alias = "bin")]
+    #[serde(alias = "bin")]
+    Binary,
+    #[value(alias = "oct")]
+    #[serde(alias = "oct")]
+    Octal,
+    #[value(alias = "dec")]
+    #[serde(alias = "dec")]
+    Decimal,
+}
+
+/// Built-in key binding sets.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeymapPreset {
+    /// Single keys, with a few vim-style ones (`j`, `k`, `g`, `G`, `gt`).
+    #[default]
+    Default,
+    Vim,
+    Emacs,
+}
+
+/// Arguments for the `undump` subcommand.
+#[derive(clap::Args, Debug)]
+pub struct UndumpArgs {
+    /// Dump to read, in any `dump` style (default: stdin).
+    pub input: Option<PathBuf>,
+
+    /// Style of the dump (default: detected; input of only hex digits is plain).
+    #[arg(long = "style", short = 's', value_enum)]
+    pub style: Option<DumpStyle>,
+
+    /// Write the binary here instead of stdout.
+    #[arg(long = "output", short = 'o')]
+    pub output: Option<PathBuf>,
+
+    /// Treat the first offset in the dump as the start of the output.
+    #[arg(long = "relative", short = 'r')]
+    pub relative: bool,
+}
+
+/// Row widths allowed by `--width`, `:set width` and auto width.
+pub const MIN_BYTES_PER_ROW: usize = 8;
+pub const MAX_BYTES_PER_ROW: usize = 32;
+
+pub fn clamp_bytes_per_row(bytes_per_row: usize) -> usize {
+    bytes_per_row.clamp(MIN_BYTES_PER_ROW, MAX_BYTES_PER_ROW)
+}
+
+pub fn parse_width(value: &str) -> Result<Width, String> {
+    if value.eq_ignore_ascii_case("auto") {
+        return Ok(Width::Auto);
+    }
+    value
+        .parse()
+        .map(Width::Fixed)
+        .map_err(|_| format!("expected a number of bytes or `auto`, got `{value}`"))
+}
+
+pub fn parse_group_size(value: &str) -> Result<usize, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|size| GROUP_SIZES.contains(size))
+        .ok_or_else(|| "group size must be 1, 2, 4 or 8".to_string())
+}
+
+/// Reject a group size from the config file that `--group` would not accept.
+pub fn check_group_size(size: usize) -> Result<()> {
+    if !GROUP_SIZES.contains(&size) {
+        bail!("group size must be 1, 2, 4 or 8, got {size}");
     }
+    Ok(())
 }
@@ -0,0 +1,589 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result, anyhow, bail};
+use clap::ValueEnum;
+
+use crate::{
+    app::{App, Grouping},
+    args::{Base, PatchFormat, Width, clamp_bytes_per_row, parse_group_size, parse_width},
+    keymap::{ACTIONS, Action},
+    parse::{parse_byte, parse_hex_bytes, parse_number},
+    template::Template,
+};
+
+/// Commands typed after `:`, with their usage. Action names are accepted as commands too.
+pub const COMMANDS: &[(&str, &str)] = &[
+    ("goto", "goto OFFSET"),
+    ("find", "find HEX BYTES | find \"TEXT\""),
+    ("fill", "fill OFFSET COUNT BYTE"),
+    ("export", "export FILE.ips|.bps|.txt"),
+    ("apply", "apply PATCH"),
+    ("template", "template FILE [OFFSET] | template off"),
+    (
+        "set",
+        "set width=N|auto group=N base=NAME little-endian[=on|off]",
+    ),
+    ("w", "w"),
+    ("q", "q[!]"),
+    ("wq", "wq"),
+];
+
+/// Options that `set` can change.
+const SETTINGS: &[&str] = &["width", "group", "base", "little-endian"];
+
+/// A parsed command line.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Scroll to an offset as shown in the offset column.
+    Goto(usize),
+    Find(Vec<u8>),
+    Fill {
+        offset: usize,
+        count: usize,
+        value: u8,
+    },
+    Set(Vec<Setting>),
+    /// Write the unsaved edits as a patch file.
+    Export(PathBuf),
+    /// Apply a patch file to the buffer as unsaved edits.
+    Apply(PathBuf),
+    /// Apply a template file at an offset (the cursor by default); `None` removes it.
+    Template(Option<(PathBuf, Option<usize>)>),
+    Write,
+    /// Quit; without `force` this fails while any buffer has unsaved edits.
+    Quit {
+        force: bool,
+    },
+    WriteQuit,
+    Action(Action),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Setting {
+    Width(Width),
+    Group(usize),
+    Base(Base),
+    LittleEndian(bool),
+}
+
+/// What the frontend has to do after a command ran.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Done,
+    /// The command moved to this byte.
+    Goto(usize),
+    Quit {
+        force: bool,
+    },
+    Action(Action),
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Self> {
+        let line = line.trim();
+        let (name, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        let mut args = rest.split_whitespace();
+        let command = match name {
+            "" => bail!("Empty command"),
+            "goto" | "g" => Command::Goto(number(args.next(), "offset")?),
+            "find" | "f" => Command::Find(pattern(rest)?),
+            "fill" => Command::Fill {
+                offset: number(args.next(), "offset")?,
+                count: number(args.next(), "count")?,
+                value: parse_byte(args.next().context("Missing byte value")?)
+                    .map_err(|err| anyhow!(err))?,
+            },
+            "set" => {
+                let settings = args.by_ref().map(setting).collect::<Result<Vec<_>>>()?;
+                if settings.is_empty() {
+                    bail!("Usage: {}", usage("set"));
+                }
+                Command::Set(settings)
+            }
+            "export" if !rest.is_empty() => Command::Export(PathBuf::from(rest)),
+            "export" => bail!("Usage: {}", usage("export")),
+            "apply" if !rest.is_empty() => Command::Apply(PathBuf::from(rest)),
+            "apply" => bail!("Usage: {}", usage("apply")),
+            "template" => Command::Template(template(rest)?),
+            "w" | "write" => Command::Write,
+            "q" | "quit" => Command::Quit { force: false },
+            "q!" | "quit!" => Command::Quit { force: true },
+            "wq" | "x" => Command::WriteQuit,
+            // `:0x400` jumps like `:goto 0x400`.
+            _ if name.starts_with(|c: char| c.is_ascii_digit()) => {
+                Command::Goto(number(Some(name), "offset")?)
+            }
+            _ => Command::Action(name.parse().map_err(|_| anyhow!("Not a command: {name}"))?),
+        };
+        if matches!(command, Command::Goto(_) | Command::Fill { .. }) && args.next().is_some() {
+            bail!("Too many arguments");
+        }
+        Ok(command)
+    }
+
+    /// Carry out the command on `app`; quitting and actions are left to the frontend.
+    pub fn run(self, app: &mut App) -> Result<Outcome> {
+        match self {
+            Command::Goto(offset) => {
+                let index = app
+                    .index_of_offset(offset)
+                    .with_context(|| format!("Offset 0x{offset:X} is outside the buffer"))?;
+                app.goto(index);
+                return Ok(Outcome::Goto(index));
+            }
+            Command::Find(pattern) => {
+                let index = app.find_next(&pattern).context("Pattern not found")?;
+                app.set_message(format!("found at 0x{:08X}", app.display_offset(index)));
+                return Ok(Outcome::Goto(index));
+            }
+            Command::Fill {
+                offset,
+                count,
+                value,
+            } => {
+                let index = app
+                    .index_of_offset(offset)
+                    .with_context(|| format!("Offset 0x{offset:X} is outside the buffer"))?;
+                app.fill(index, count, value)?;
+            }
+            Command::Set(settings) => {
+                for setting in settings {
+                    apply_setting(app, setting);
+                }
+            }
+            Command::Export(path) => {
+                let count = app.export_patch(&path)?;
+                app.set_message(format!("exported {count} edits to {}", path.display()));
+            }
+            Command::Apply(path) => {
+                let (count, format) = app.apply_patch(&path)?;
+                let mut message = format!("applied {} ({count} bytes changed)", path.display());
+                if format == PatchFormat::Ips {
+                    message.push_str("; IPS has no original bytes, so it was not verified");
+                }
+                app.set_message(message);
+            }
+            Command::Template(None) => {
+                app.clear_template();
+                app.set_message("template removed".to_string());
+            }
+            Command::Template(Some((path, offset))) => {
+                let index = match offset {
+                    Some(offset) => app
+                        .index_of_offset(offset)
+                        .with_context(|| format!("Offset 0x{offset:X} is outside the buffer"))?,
+                    None => app.cursor().unwrap_or(0),
+                };
+                let template = Template::load(&path)?;
+                let offset = app.display_offset(index);
+                let root = app.set_template(template, index)?;
+                let message = format!(
+                    "template {}: {} bytes from 0x{offset:08X}",
+                    root.name,
+                    root.range.len()
+                );
+                app.set_message(message);
+            }
+            Command::Write => app.save()?,
+            Command::Quit { force } => return Ok(Outcome::Quit { force }),
+            Command::WriteQuit => {
+                app.save()?;
+                return Ok(Outcome::Quit { force: false });
+            }
+            Command::Action(action) => return Ok(Outcome::Action(action)),
+        }
+        Ok(Outcome::Done)
+    }
+}
+
+/// Whether the command needs arguments after its name.
+pub fn takes_arguments(name: &str) -> bool {
+    usage(name).contains(' ')
+}
+
+/// How well `query` matches `text` as a subsequence, ignoring case; higher is better.
+/// Runs of consecutive characters and matches at word starts score extra.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.chars().map(|c| c.to_ascii_lowercase()).collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for wanted in query.chars().map(|c| c.to_ascii_lowercase()) {
+        if wanted == ' ' {
+            continue;
+        }
+        let found = position + text[position..].iter().position(|&c| c == wanted)?;
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || matches!(text[found - 1], ' ' | '-') {
+            score += 3;
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+    // Prefer shorter names when the matches are otherwise equal.
+    Some(score * 100 - text.len() as i32)
+}
+
+fn usage(name: &str) -> &'static str {
+    COMMANDS
+        .iter()
+        .find(|(command, _)| *command == name)
+        .map_or("", |(_, usage)| usage)
+}
+
+fn number(arg: Option<&str>, what: &str) -> Result<usize> {
+    let arg = arg.with_context(|| format!("Missing {what}"))?;
+    parse_number(arg).map_err(|err| anyhow!(err))
+}
+
+/// `"text"` searches for the text, anything else for hex bytes.
+fn pattern(arg: &str) -> Result<Vec<u8>> {
+    if arg.is_empty() {
+        bail!("Usage: {}", usage("find"));
+    }
+    match arg.strip_prefix('"') {
+        Some(text) => Ok(text.strip_suffix('"').unwrap_or(text).as_bytes().to_vec()),
+        None => parse_hex_bytes(arg).map_err(|err| anyhow!(err)),
+    }
+}
+
+/// `FILE [OFFSET]` or `off`. The path may contain spaces; a trailing number is the offset.
+fn template(rest: &str) -> Result<Option<(PathBuf, Option<usize>)>> {
+    if rest.is_empty() {
+        bail!("Usage: {}", usage("template"));
+    }
+    if rest == "off" {
+        return Ok(None);
+    }
+    let (path, offset) = match rest.rsplit_once(char::is_whitespace) {
+        Some((path, offset)) => match parse_number(offset) {
+            Ok(offset) => (path.trim_end(), Some(offset)),
+            Err(_) => (rest, None),
+        },
+        None => (rest, None),
+    };
+    Ok(Some((PathBuf::from(path), offset)))
+}
+
+fn setting(arg: &str) -> Result<Setting> {
+    let (name, value) = match arg.split_once('=') {
+        Some((name, value)) => (name, Some(value)),
+        None => (arg, None),
+    };
+    let invalid = |err: String| anyhow!("{name}: {err}");
+    Ok(match (name, value) {
+        ("width", Some(value)) => Setting::Width(parse_width(value).map_err(invalid)?),
+        ("group", Some(value)) => Setting::Group(parse_group_size(value).map_err(invalid)?),
+        ("base", Some(value)) => Setting::Base(Base::from_str(value, true).map_err(invalid)?),
+        ("little-endian", None) => Setting::LittleEndian(true),
+        ("nolittle-endian", None) => Setting::LittleEndian(false),
+        ("little-endian", Some(value)) => Setting::LittleEndian(match value {
+            "on" | "true" | "yes" => true,
+            "off" | "false" | "no" => false,
+            _ => bail!("little-endian: expected on or off, got `{value}`"),
+        }),
+        _ if SETTINGS.contains(&name) => bail!("Usage: set {name}=VALUE"),
+        _ => bail!("Unknown option: {name}"),
+    })
+}
+
+fn apply_setting(app: &mut App, setting: Setting) {
+    match setting {
+        Setting::Width(Width::Auto) => app.set_auto_width(true),
+        Setting::Width(Width::Fixed(bytes_per_row)) => {
+            app.set_auto_width(false);
+            app.set_bytes_per_row(clamp_bytes_per_row(bytes_per_row));
+        }
+        Setting::Group(size) => app.set_grouping(Grouping {
+            size,
+            ..app.grouping()
+        }),
+        Setting::Base(base) => app.set_base(base),
+        Setting::LittleEndian(little_endian) => app.set_grouping(Grouping {
+            little_endian,
+            ..app.grouping()
+        }),
+    }
+}
+
+/// Text being typed after `:`, with the history of earlier commands.
+#[derive(Default)]
+pub struct CommandLine {
+    input: Option<String>,
+    history: Vec<String>,
+    /// Position while browsing the history with Up/Down.
+    history_pos: Option<usize>,
+    /// Candidates offered by the last Tab.
+    completions: Option<String>,
+    /// Why the last command failed, shown until the next key press.
+    error: Option<String>,
+}
+
+impl CommandLine {
+    pub fn open(&mut self) {
+        self.input = Some(String::new());
+        self.history_pos = None;
+        self.completions = None;
+        self.error = None;
+    }
+
+    pub fn close(&mut self) {
+        self.input = None;
+        self.completions = None;
+    }
+
+    /// The text typed so far, or `None` when the command line is closed.
+    pub fn input(&self) -> Option<&str> {
+        self.input.as_deref()
+    }
+
+    pub fn completions(&self) -> Option<&str> {
+        self.completions.as_deref()
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn set_error(&mut self, error: Option<String>) {
+        self.error = error;
+    }
+
+    pub fn push(&mut self, c: char) {
+        if let Some(input) = &mut self.input {
+            input.push(c);
+            self.completions = None;
+        }
+    }
+
+    /// Delete the last character; deleting from an empty line closes it.
+    pub fn backspace(&mut self) {
+        self.completions = None;
+        match &mut self.input {
+            Some(input) if !input.is_empty() => {
+                input.pop();
+            }
+            _ => self.close(),
+        }
+    }
+
+    /// Close the line and return what was typed, remembering it in the history.
+    pub fn submit(&mut self) -> Option<String> {
+        let line = self.input.take()?;
+        if !line.trim().is_empty() && self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+        }
+        Some(line)
+    }
+
+    /// Step through the history; `back` goes to older entries.
+    pub fn recall(&mut self, back: bool) {
+        if self.input.is_none() || self.history.is_empty() {
+            return;
+        }
+        self.completions = None;
+        let last = self.history.len() - 1;
+        self.history_pos = match (self.history_pos, back) {
+            (None, true) => Some(last),
+            (None, false) => None,
+            (Some(pos), true) => Some(pos.saturating_sub(1)),
+            (Some(pos), false) if pos < last => Some(pos + 1),
+            (Some(_), false) => None,
+        };
+        self.input = Some(match self.history_pos {
+            Some(pos) => self.history[pos].clone(),
+            None => String::new(),
+        });
+    }
+
+    /// Complete the command or `set` option being typed. With several candidates the
+    /// common prefix is filled in and the candidates are listed in `completions`.
+    pub fn complete(&mut self) {
+        let Some(input) = &mut self.input else {
+            return;
+        };
+        let (prefix, word, candidates): (_, _, Vec<String>) = match input.rsplit_once(' ') {
+            Some((head, word)) if head.trim_start().starts_with("set") => (
+                format!("{head} "),
+                word.to_string(),
+                SETTINGS.iter().map(|name| format!("{name}=")).collect(),
+            ),
+            Some(_) => return,
+            None => (
+                String::new(),
+                input.clone(),
+                COMMANDS
+                    .iter()
+                    .map(|(name, _)| name.to_string())
+                    .chain(ACTIONS.iter().map(|(_, name, _)| name.to_string()))
+                    .collect(),
+            ),
+        };
+        let matches: Vec<&String> = candidates
+            .iter()
+            .filter(|candidate| candidate.starts_with(&word))
+            .collect();
+        let Some(first) = matches.first() else {
+            self.completions = Some(format!("no completions for `{word}`"));
+            return;
+        };
+        let common = matches.iter().fold(first.as_str(), |common, candidate| {
+            let len = common
+                .bytes()
+                .zip(candidate.bytes())
+                .take_while(|(a, b)| a == b)
+                .count();
+            &common[..len]
+        });
+        *input = format!("{prefix}{common}");
+        if matches.len() == 1 {
+            if !common.ends_with('=') && prefix.is_empty() {
+                input.push(' ');
+            }
+            self.completions = None;
+        } else {
+            let names: Vec<&str> = matches.iter().map(|name| name.as_str()).collect();
+            self.completions = Some(names.join("  "));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Command {
+        Command::parse(line).unwrap()
+    }
+
+    fn error(line: &str) -> String {
+        format!("{:#}", Command::parse(line).unwrap_err())
+    }
+
+    #[test]
+    fn commands_parse_their_arguments() {
+        assert_eq!(parse("goto 0x10"), Command::Goto(16));
+        assert_eq!(parse("  g 32 "), Command::Goto(32));
+        assert_eq!(parse("0x400"), Command::Goto(0x400));
+        assert_eq!(parse("find de ad"), Command::Find(vec![0xDE, 0xAD]));
+        assert_eq!(parse("f \"a b\""), Command::Find(b"a b".to_vec()));
+        assert_eq!(
+            parse("fill 0x10 4 255"),
+            Command::Fill {
+                offset: 16,
+                count: 4,
+                value: 255
+            }
+        );
+        assert_eq!(parse("apply a b.ips"), Command::Apply("a b.ips".into()));
+        assert_eq!(parse("q!"), Command::Quit { force: true });
+        assert_eq!(parse("x"), Command::WriteQuit);
+        assert_eq!(
+            parse("split-vertical"),
+            Command::Action(Action::SplitVertical)
+        );
+    }
+
+    #[test]
+    fn bad_commands_explain_themselves() {
+        assert_eq!(error(""), "Empty command");
+        assert_eq!(error("goto"), "Missing offset");
+        assert_eq!(error("goto 1 2"), "Too many arguments");
+        assert_eq!(error("fill 0 1"), "Missing byte value");
+        assert_eq!(error("export"), "Usage: export FILE.ips|.bps|.txt");
+        assert_eq!(error("find"), format!("Usage: {}", usage("find")));
+        assert_eq!(error("frobnicate"), "Not a command: frobnicate");
+    }
+
+    #[test]
+    fn template_takes_a_trailing_offset() {
+        assert_eq!(parse("template off"), Command::Template(None));
+        assert_eq!(
+            parse("template png.tpl 0x20"),
+            Command::Template(Some(("png.tpl".into(), Some(0x20))))
+        );
+        assert_eq!(
+            parse("template my file.tpl"),
+            Command::Template(Some(("my file.tpl".into(), None)))
+        );
+        assert!(Command::parse("template").is_err());
+    }
+
+    #[test]
+    fn set_parses_each_option() {
+        assert_eq!(
+            parse("set width=auto group=4 base=octal nolittle-endian"),
+            Command::Set(vec![
+                Setting::Width(Width::Auto),
+                Setting::Group(4),
+                Setting::Base(Base::Octal),
+                Setting::LittleEndian(false),
+            ])
+        );
+        assert_eq!(
+            parse("set little-endian=on width=24"),
+            Command::Set(vec![
+                Setting::LittleEndian(true),
+                Setting::Width(Width::Fixed(24)),
+            ])
+        );
+        assert_eq!(error("set"), format!("Usage: {}", usage("set")));
+        assert_eq!(error("set group"), "Usage: set group=VALUE");
+        assert_eq!(error("set color=red"), "Unknown option: color");
+        assert!(Command::parse("set group=3").is_err());
+    }
+
+    #[test]
+    fn fuzzy_score_prefers_tight_and_word_start_matches() {
+        assert_eq!(fuzzy_score("xyz", "goto"), None);
+        assert_eq!(fuzzy_score("", "goto"), Some(-4));
+        let split = fuzzy_score("sv", "split-vertical").unwrap();
+        let scattered = fuzzy_score("sv", "toggle-status-bar-view").unwrap_or(i32::MIN);
+        assert!(split > scattered);
+        assert!(fuzzy_score("GO", "goto") > fuzzy_score("go", "cycle-group"));
+        assert!(takes_arguments("goto"));
+        assert!(!takes_arguments("w"));
+    }
+
+    #[test]
+    fn completion_fills_commands_and_settings() {
+        let mut line = CommandLine::default();
+        line.open();
+        "tem".chars().for_each(|c| line.push(c));
+        line.complete();
+        assert_eq!(line.input(), Some("template "));
+
+        line.open();
+        "set gr".chars().for_each(|c| line.push(c));
+        line.complete();
+        assert_eq!(line.input(), Some("set group="));
+
+        line.open();
+        line.push('q');
+        line.complete();
+        assert_eq!(line.input(), Some("q"));
+        assert!(line.completions().is_some());
+    }
+
+    #[test]
+    fn history_recalls_submitted_lines() {
+        let mut line = CommandLine::default();
+        for text in ["goto 1", "goto 2", "goto 2"] {
+            line.open();
+            text.chars().for_each(|c| line.push(c));
+            line.submit();
+        }
+        line.open();
+        line.recall(true);
+        assert_eq!(line.input(), Some("goto 2"));
+        line.recall(true);
+        assert_eq!(line.input(), Some("goto 1"));
+        line.recall(false);
+        line.recall(false);
+        assert_eq!(line.input(), Some(""));
+    }
+}
@@ -0,0 +1,67 @@
+pub mod apply;
+pub mod diff;
+pub mod dump;
+pub mod patch;
+
+use std::{
+    io::{IsTerminal, Write, stdout},
+    ops::Range,
+};
+
+use anyhow::Result;
+use crossterm::style::{ResetColor, SetForegroundColor};
+
+use crate::{
+    app::{Grouping, format_line},
+    args::Base,
+    config::Config,
+    theme::{Rgb, no_color_env},
+};
+
+/// Colors for removed (`-`) and added (`+`) rows, taken from the configured theme when
+/// stdout is a terminal. An unreadable config file falls back to the default theme.
+pub(crate) fn diff_colors() -> Option<(Rgb, Rgb)> {
+    if !stdout().is_terminal() || no_color_env() {
+        return None;
+    }
+    let theme = Config::load(None)
+        .and_then(|config| config.theme(None))
+        .unwrap_or_default();
+    Some((theme.diff_removed, theme.diff_added))
+}
+
+/// Print every row of `bytes` touched by `range`, aligned to row boundaries and prefixed
+/// with `marker` and drawn in `color` if given.
+pub(crate) fn write_rows(
+    out: &mut impl Write,
+    marker: char,
+    bytes: &[u8],
+    range: &Range<usize>,
+    width: usize,
+    color: Option<Rgb>,
+) -> Result<()> {
+    if range.is_empty() {
+        return Ok(());
+    }
+    let mut offset = range.start - range.start % width;
+    while offset < range.end.min(bytes.len()) {
+        let end = (offset + width).min(bytes.len());
+        let line = format_line(
+            offset,
+            &bytes[offset..end],
+            width,
+            Grouping::default(),
+            Base::Hex,
+        );
+        match color {
+            Some(color) => writeln!(
+                out,
+                "{}{marker}{line}{ResetColor}",
+                SetForegroundColor(color.into())
+            )?,
+            None => writeln!(out, "{marker}{line}")?,
+        }
+        offset += width;
+    }
+    Ok(())
+}
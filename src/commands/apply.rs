@@ -0,0 +1,69 @@
+use std::{
+    fs,
+    io::{self, Write},
+};
+
+use anyhow::{Context, Result};
+
+use crate::{
+    args::{ApplyArgs, PatchFormat},
+    commands::{diff_colors, write_rows},
+    diff::{self, DiffKind},
+    io::write_with_backup,
+    patchfile,
+};
+
+/// Run `apply`: decode the patch, verify it against the file and write the result.
+pub fn run(args: &ApplyArgs) -> Result<()> {
+    let data = fs::read(&args.patch)
+        .with_context(|| format!("Failed to read patch file: {}", args.patch.display()))?;
+    let patch = patchfile::decode(&data)
+        .with_context(|| format!("Failed to parse patch file: {}", args.patch.display()))?;
+    let original = fs::read(&args.file)
+        .with_context(|| format!("Failed to read input file: {}", args.file.display()))?;
+
+    if patch.format() == PatchFormat::Ips {
+        eprintln!("Warning: IPS patches carry no original bytes; the file cannot be verified.");
+    }
+    let patched = patch
+        .apply(&original)
+        .with_context(|| format!("Patch does not apply to {}", args.file.display()))?;
+
+    if args.dry_run {
+        let mut out = io::stdout().lock();
+        let width = args.clamped_bytes_per_row();
+        let (removed, added) = diff_colors().unzip();
+        for op in diff::compare(&original, &patched) {
+            if op.kind == DiffKind::Equal {
+                continue;
+            }
+            writeln!(
+                out,
+                "@@ -0x{:08X},{} +0x{:08X},{} @@",
+                op.left.start,
+                op.left.len(),
+                op.right.start,
+                op.right.len()
+            )?;
+            write_rows(&mut out, '-', &original, &op.left, width, removed)?;
+            write_rows(&mut out, '+', &patched, &op.right, width, added)?;
+        }
+        return Ok(());
+    }
+
+    let backup = write_with_backup(&args.file, &patched, !args.no_backup)?;
+    match backup {
+        Some(path) => println!(
+            "Applied {} to {} (backup: {})",
+            args.patch.display(),
+            args.file.display(),
+            path.display()
+        ),
+        None => println!(
+            "Applied {} to {}",
+            args.patch.display(),
+            args.file.display()
+        ),
+    }
+    Ok(())
+}
@@ -0,0 +1,168 @@
+use std::{
+    fs,
+    io::{self, Write},
+    ops::Range,
+    process::ExitCode,
+};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::{
+    args::{DiffArgs, DiffFormat},
+    commands::{diff_colors, write_rows},
+    diff::{self, DiffKind, DiffOp},
+};
+
+/// Run `diff`, mapping the outcome to the conventional `cmp`/`diff` exit codes.
+pub fn run(args: &DiffArgs) -> ExitCode {
+    match compare_files(args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(err) => {
+            eprintln!("Error: {err:?}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Returns whether the files are identical.
+fn compare_files(args: &DiffArgs) -> Result<bool> {
+    let left = fs::read(&args.left)
+        .with_context(|| format!("Failed to read input file: {}", args.left.display()))?;
+    let right = fs::read(&args.right)
+        .with_context(|| format!("Failed to read input file: {}", args.right.display()))?;
+
+    let ops = if args.align {
+        diff::align(&left, &right)
+    } else {
+        diff::compare(&left, &right)
+    };
+    let changes: Vec<&DiffOp> = ops.iter().filter(|op| op.kind != DiffKind::Equal).collect();
+
+    let mut out = io::stdout().lock();
+    match args.format {
+        DiffFormat::Text => write_text(&mut out, &changes)?,
+        DiffFormat::Json => write_json(&mut out, args, &left, &right, &changes)?,
+        DiffFormat::Hex => write_hex(&mut out, args, &left, &right, &changes)?,
+    }
+    out.flush()?;
+
+    Ok(changes.is_empty())
+}
+
+fn kind_name(kind: DiffKind) -> &'static str {
+    match kind {
+        DiffKind::Equal => "equal",
+        DiffKind::Replace => "replace",
+        DiffKind::Delete => "delete",
+        DiffKind::Insert => "insert",
+    }
+}
+
+fn write_text(out: &mut impl Write, changes: &[&DiffOp]) -> Result<()> {
+    for op in changes {
+        writeln!(
+            out,
+            "{:<7} 0x{:08X}..0x{:08X} -> 0x{:08X}..0x{:08X} ({} -> {} bytes)",
+            kind_name(op.kind),
+            op.left.start,
+            op.left.end,
+            op.right.start,
+            op.right.end,
+            op.left.len(),
+            op.right.len()
+        )?;
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    left: String,
+    right: String,
+    left_size: usize,
+    right_size: usize,
+    aligned: bool,
+    identical: bool,
+    ranges: Vec<JsonRange<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonRange<'a> {
+    kind: &'a str,
+    left: JsonSpan,
+    right: JsonSpan,
+}
+
+#[derive(Serialize)]
+struct JsonSpan {
+    start: usize,
+    end: usize,
+}
+
+impl From<&Range<usize>> for JsonSpan {
+    fn from(range: &Range<usize>) -> Self {
+        Self {
+            start: range.start,
+            end: range.end,
+        }
+    }
+}
+
+fn write_json(
+    out: &mut impl Write,
+    args: &DiffArgs,
+    left: &[u8],
+    right: &[u8],
+    changes: &[&DiffOp],
+) -> Result<()> {
+    let report = JsonReport {
+        left: args.left.display().to_string(),
+        right: args.right.display().to_string(),
+        left_size: left.len(),
+        right_size: right.len(),
+        aligned: args.align,
+        identical: changes.is_empty(),
+        ranges: changes
+            .iter()
+            .map(|op| JsonRange {
+                kind: kind_name(op.kind),
+                left: (&op.left).into(),
+                right: (&op.right).into(),
+            })
+            .collect(),
+    };
+    serde_json::to_writer_pretty(&mut *out, &report)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+fn write_hex(
+    out: &mut impl Write,
+    args: &DiffArgs,
+    left: &[u8],
+    right: &[u8],
+    changes: &[&DiffOp],
+) -> Result<()> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+    let width = args.clamped_bytes_per_row();
+    let (removed, added) = diff_colors().unzip();
+    writeln!(out, "--- {}", args.left.display())?;
+    writeln!(out, "+++ {}", args.right.display())?;
+    for op in changes {
+        writeln!(
+            out,
+            "@@ -0x{:08X},{} +0x{:08X},{} @@",
+            op.left.start,
+            op.left.len(),
+            op.right.start,
+            op.right.len()
+        )?;
+        write_rows(out, '-', left, &op.left, width, removed)?;
+        write_rows(out, '+', right, &op.right, width, added)?;
+    }
+    Ok(())
+}
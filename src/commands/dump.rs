@@ -0,0 +1,50 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+
+use crate::{
+    args::{DumpArgs, UndumpArgs},
+    dump::{format_dump, parse_dump},
+    io::read_window,
+};
+
+/// Run `dump`, reading only the requested window of the file.
+pub fn run_dump(args: &DumpArgs) -> Result<()> {
+    let bytes = read_window(&args.path, args.offset, args.length)?;
+
+    let text = format_dump(
+        args.style,
+        args.offset,
+        &bytes,
+        args.clamped_bytes_per_row(),
+    );
+    let mut out = io::stdout().lock();
+    out.write_all(text.as_bytes())?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Run `undump`, reading a dump from a file or stdin.
+pub fn run_undump(args: &UndumpArgs) -> Result<()> {
+    let text = match args.input.as_deref() {
+        Some(path) if path != Path::new("-") => fs::read_to_string(path)
+            .with_context(|| format!("Failed to read input file: {}", path.display()))?,
+        _ => io::read_to_string(io::stdin()).context("Failed to read dump from stdin")?,
+    };
+    let bytes = parse_dump(&text, args.style, args.relative)?;
+
+    match &args.output {
+        Some(path) => fs::write(path, &bytes)
+            .with_context(|| format!("Failed to write output file: {}", path.display()))?,
+        None => {
+            let mut out = io::stdout().lock();
+            out.write_all(&bytes)?;
+            out.flush()?;
+        }
+    }
+    Ok(())
+}
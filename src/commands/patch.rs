@@ -0,0 +1,119 @@
+use std::{
+    fs,
+    io::{self, Write},
+};
+
+use anyhow::{Context, Result, bail};
+use clap::ArgMatches;
+
+use crate::{
+    args::PatchArgs,
+    commands::{diff_colors, write_rows},
+    io::write_with_backup,
+    patchfile,
+};
+
+/// A single edit: `bytes` written starting at `offset`.
+#[derive(Clone, Debug)]
+pub struct Edit {
+    pub offset: usize,
+    pub bytes: Vec<u8>,
+}
+
+/// Run `patch`. `matches` are the subcommand's matches, used to recover the order of
+/// `--write` and `--fill` so each can be paired with its `--offset`.
+pub fn run(args: &PatchArgs, matches: &ArgMatches) -> Result<()> {
+    let edits = collect_edits(args, matches)?;
+    let original = fs::read(&args.file)
+        .with_context(|| format!("Failed to read input file: {}", args.file.display()))?;
+
+    let mut patched = original.clone();
+    for edit in &edits {
+        apply_edit(&mut patched, edit)?;
+    }
+
+    if args.dry_run {
+        let mut out = io::stdout().lock();
+        let width = args.clamped_bytes_per_row();
+        let (removed, added) = diff_colors().unzip();
+        for edit in &edits {
+            let range = edit.offset..edit.offset + edit.bytes.len();
+            writeln!(out, "@@ 0x{:08X},{} @@", edit.offset, edit.bytes.len())?;
+            write_rows(&mut out, '-', &original, &range, width, removed)?;
+            write_rows(&mut out, '+', &patched, &range, width, added)?;
+        }
+        return Ok(());
+    }
+
+    if let Some(export) = &args.export {
+        let format = args
+            .export_format
+            .unwrap_or_else(|| patchfile::format_for_path(export));
+        let data = patchfile::encode(format, &original, &patched)?;
+        fs::write(export, data)
+            .with_context(|| format!("Failed to write patch file: {}", export.display()))?;
+        println!("Exported {} edits to {}", edits.len(), export.display());
+        return Ok(());
+    }
+
+    let backup = write_with_backup(&args.file, &patched, !args.no_backup)?;
+    let written: usize = edits.iter().map(|edit| edit.bytes.len()).sum();
+    match backup {
+        Some(path) => println!(
+            "Patched {written} bytes in {} (backup: {})",
+            args.file.display(),
+            path.display()
+        ),
+        None => println!("Patched {written} bytes in {}", args.file.display()),
+    }
+    Ok(())
+}
+
+/// Write `edit` into `bytes`; edits may extend the file but must start within it.
+pub fn apply_edit(bytes: &mut Vec<u8>, edit: &Edit) -> Result<()> {
+    if edit.offset > bytes.len() {
+        bail!(
+            "Offset 0x{:X} is past the end of the file (size 0x{:X})",
+            edit.offset,
+            bytes.len()
+        );
+    }
+    let end = edit.offset + edit.bytes.len();
+    if end > bytes.len() {
+        bytes.resize(end, 0);
+    }
+    bytes[edit.offset..end].copy_from_slice(&edit.bytes);
+    Ok(())
+}
+
+fn collect_edits(args: &PatchArgs, matches: &ArgMatches) -> Result<Vec<Edit>> {
+    if args.fills.len() != args.counts.len() {
+        bail!("Each --fill needs exactly one --count");
+    }
+
+    let mut ops: Vec<(usize, Vec<u8>)> = Vec::new();
+    let write_indices = matches.indices_of("writes").into_iter().flatten();
+    for (index, bytes) in write_indices.zip(&args.writes) {
+        ops.push((index, bytes.clone()));
+    }
+    let fill_indices = matches.indices_of("fills").into_iter().flatten();
+    for (index, (&value, &count)) in fill_indices.zip(args.fills.iter().zip(&args.counts)) {
+        ops.push((index, vec![value; count]));
+    }
+    ops.sort_by_key(|(index, _)| *index);
+
+    if ops.len() != args.offsets.len() {
+        bail!(
+            "Got {} --offset values but {} --write/--fill values; each offset needs one edit",
+            args.offsets.len(),
+            ops.len()
+        );
+    }
+
+    Ok(args
+        .offsets
+        .iter()
+        .zip(ops)
+        .map(|(&offset, (_, bytes))| Edit { offset, bytes })
+        .collect())
+}
@@ -0,0 +1,171 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+
+use crate::{
+    args::{Base, KeymapPreset, Width, check_group_size},
+    keymap::{KeySequence, Keymap},
+    theme::{BUILTIN_THEMES, Theme, ThemeSpec},
+};
+
+/// Settings read from `config.toml`. Command line options win over the defaults here.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// Bytes per row, or `"auto"`.
+    pub width: Option<Width>,
+    /// Bytes per group in the hex column.
+    pub group: Option<usize>,
+    pub little_endian: bool,
+    /// Number base for the byte column.
+    pub base: Option<Base>,
+    /// Do not capture the mouse in the TUI.
+    pub no_mouse: bool,
+    /// Theme used unless `--theme` picks another.
+    pub theme: Option<String>,
+    /// User-defined themes by name.
+    pub themes: BTreeMap<String, ThemeSpec>,
+    /// Key bindings to start from.
+    pub keymap: Option<KeymapPreset>,
+    /// Extra bindings from key sequences to action names; `"none"` unbinds a key.
+    pub keys: BTreeMap<String, String>,
+}
+
+/// `~/.config/rust-hex-viewer/config.toml`, or the platform's equivalent.
+pub fn default_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("rust-hex-viewer").join("config.toml"))
+}
+
+impl Config {
+    /// Read the config file at `path`, or at the default location if `path` is `None`.
+    /// A missing file at the default location is not an error.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match default_path() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(Self::default()),
+            },
+        };
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+        let config: Self = toml::from_str(&text)
+            .with_context(|| format!("Invalid config file: {}", path.display()))?;
+        config
+            .check()
+            .with_context(|| format!("Invalid config file: {}", path.display()))?;
+        Ok(config)
+    }
+
+    fn check(&self) -> Result<()> {
+        if let Some(size) = self.group {
+            check_group_size(size)?;
+        }
+        self.keymap(self.keymap.unwrap_or_default())?;
+        Ok(())
+    }
+
+    /// The `preset` bindings with the `[keys]` table applied on top.
+    pub fn keymap(&self, preset: KeymapPreset) -> Result<Keymap> {
+        self.bind_keys(Keymap::preset(preset))
+    }
+
+    /// The GUI bindings with the `[keys]` table applied on top.
+    pub fn gui_keymap(&self) -> Result<Keymap> {
+        self.bind_keys(Keymap::gui())
+    }
+
+    fn bind_keys(&self, mut keymap: Keymap) -> Result<Keymap> {
+        for (keys, action) in &self.keys {
+            let sequence: KeySequence = keys
+                .parse()
+                .with_context(|| format!("Invalid key binding `{keys}`"))?;
+            let action = match action.as_str() {
+                "none" => None,
+                name => Some(
+                    name.parse()
+                        .with_context(|| format!("Invalid action for `{keys}`"))?,
+                ),
+            };
+            keymap.bind(sequence, action);
+        }
+        Ok(keymap)
+    }
+
+    /// Resolve the theme called `name`, falling back to the configured theme and then to
+    /// `dark`.
+    pub fn theme(&self, name: Option<&str>) -> Result<Theme> {
+        let name = name.or(self.theme.as_deref()).unwrap_or("dark");
+        self.resolve_theme(name, 0)
+    }
+
+    fn resolve_theme(&self, name: &str, depth: usize) -> Result<Theme> {
+        if depth > self.themes.len() {
+            bail!("Theme `{name}` extends itself");
+        }
+        let Some(spec) = self.themes.get(name) else {
+            return Theme::builtin(name).with_context(|| {
+                format!(
+                    "Unknown theme `{name}` (built in: {}; or define [themes.{name}] in the config file)",
+                    BUILTIN_THEMES.join(", ")
+                )
+            });
+        };
+        // A table named after a built-in theme tweaks that theme.
+        let base = spec.extends.as_deref().unwrap_or("dark");
+        let mut theme = match Theme::builtin(name) {
+            Some(builtin) if base == name || spec.extends.is_none() => builtin,
+            _ => self.resolve_theme(base, depth + 1)?,
+        };
+        theme.apply(spec);
+        Ok(theme)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keymap::Action;
+
+    fn lookup(keymap: &Keymap, keys: &str) -> Option<Action> {
+        let keys: KeySequence = keys.parse().unwrap();
+        keymap.lookup(&keys.0).action
+    }
+
+    #[test]
+    fn keys_table_applies_to_every_preset_and_the_gui() {
+        let config: Config = toml::from_str(
+            r#"
+            [keys]
+            "ctrl+d" = "page-down"
+            "ctrl+q" = "none"
+            "#,
+        )
+        .unwrap();
+        for keymap in [
+            config.keymap(KeymapPreset::Default).unwrap(),
+            config.keymap(KeymapPreset::Vim).unwrap(),
+            config.gui_keymap().unwrap(),
+        ] {
+            assert_eq!(lookup(&keymap, "ctrl+d"), Some(Action::PageDown));
+            assert_eq!(lookup(&keymap, "ctrl+q"), None);
+        }
+        assert_eq!(
+            lookup(&config.gui_keymap().unwrap(), "ctrl+o"),
+            Some(Action::Open)
+        );
+    }
+
+    #[test]
+    fn bad_bindings_are_rejected() {
+        let config: Config = toml::from_str("[keys]\n\"ctrl+d\" = \"fly\"\n").unwrap();
+        assert!(config.check().is_err());
+        let config: Config = toml::from_str("[keys]\n\"hyper+d\" = \"quit\"\n").unwrap();
+        assert!(config.check().is_err());
+    }
+}
@@ -0,0 +1,470 @@
+use std::{collections::HashMap, ops::Range};
+
+/// Block size used to find anchors between two large buffers.
+const ANCHOR_BLOCK: usize = 32;
+/// Regions whose combined length is at most this are aligned with Myers.
+const MYERS_MAX_LEN: usize = 8 * 1024;
+/// Upper bound on the Myers edit distance before giving up on a region.
+const MYERS_MAX_EDITS: usize = 512;
+
+/// How a pair of ranges relates between the left and right buffers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiffKind {
+    Equal,
+    Replace,
+    /// Bytes only present on the left; the right range is empty.
+    Delete,
+    /// Bytes only present on the right; the left range is empty.
+    Insert,
+}
+
+/// A single step of a comparison, covering `left` in the first buffer and `right` in the second.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiffOp {
+    pub kind: DiffKind,
+    pub left: Range<usize>,
+    pub right: Range<usize>,
+}
+
+impl DiffOp {
+    fn new(kind: DiffKind, left: Range<usize>, right: Range<usize>) -> Self {
+        Self { kind, left, right }
+    }
+}
+
+/// Compare two buffers byte for byte; any length difference is reported at the end.
+pub fn compare(left: &[u8], right: &[u8]) -> Vec<DiffOp> {
+    let common = left.len().min(right.len());
+    let mut ops = Vec::new();
+    let mut start = 0;
+    while start < common {
+        let equal = left[start] == right[start];
+        let mut end = start + 1;
+        while end < common && (left[end] == right[end]) == equal {
+            end += 1;
+        }
+        let kind = if equal {
+            DiffKind::Equal
+        } else {
+            DiffKind::Replace
+        };
+        ops.push(DiffOp::new(kind, start..end, start..end));
+        start = end;
+    }
+    if left.len() > common {
+        ops.push(DiffOp::new(
+            DiffKind::Delete,
+            common..left.len(),
+            common..common,
+        ));
+    } else if right.len() > common {
+        ops.push(DiffOp::new(
+            DiffKind::Insert,
+            common..common,
+            common..right.len(),
+        ));
+    }
+    ops
+}
+
+/// Compare two buffers allowing for inserted and removed data.
+///
+/// Small regions are aligned exactly with Myers' algorithm; large ones are first split
+/// on rolling-hash anchors so multi-megabyte images stay tractable.
+pub fn align(left: &[u8], right: &[u8]) -> Vec<DiffOp> {
+    let mut ops = Vec::new();
+    align_region(left, right, 0..left.len(), 0..right.len(), &mut ops);
+    normalize(ops)
+}
+
+fn align_region(
+    left: &[u8],
+    right: &[u8],
+    mut l: Range<usize>,
+    mut r: Range<usize>,
+    ops: &mut Vec<DiffOp>,
+) {
+    let prefix = common_prefix(&left[l.clone()], &right[r.clone()]);
+    if prefix > 0 {
+        ops.push(DiffOp::new(
+            DiffKind::Equal,
+            l.start..l.start + prefix,
+            r.start..r.start + prefix,
+        ));
+        l.start += prefix;
+        r.start += prefix;
+    }
+    let suffix = common_suffix(&left[l.clone()], &right[r.clone()]);
+    let tail = DiffOp::new(
+        DiffKind::Equal,
+        l.end - suffix..l.end,
+        r.end - suffix..r.end,
+    );
+    l.end -= suffix;
+    r.end -= suffix;
+
+    if l.is_empty() || r.is_empty() {
+        push_unmatched(ops, l, r);
+    } else if l.len() + r.len() <= MYERS_MAX_LEN {
+        if !myers(left, right, l.clone(), r.clone(), ops) {
+            push_unmatched(ops, l, r);
+        }
+    } else {
+        let anchors = find_anchors(left, right, l.clone(), r.clone());
+        if anchors.is_empty() {
+            push_unmatched(ops, l, r);
+        } else {
+            let (mut li, mut ri) = (l.start, r.start);
+            for (la, ra, len) in anchors {
+                align_region(left, right, li..la, ri..ra, ops);
+                ops.push(DiffOp::new(DiffKind::Equal, la..la + len, ra..ra + len));
+                li = la + len;
+                ri = ra + len;
+            }
+            align_region(left, right, li..l.end, ri..r.end, ops);
+        }
+    }
+
+    if suffix > 0 {
+        ops.push(tail);
+    }
+}
+
+fn push_unmatched(ops: &mut Vec<DiffOp>, l: Range<usize>, r: Range<usize>) {
+    let kind = match (l.is_empty(), r.is_empty()) {
+        (true, true) => return,
+        (false, true) => DiffKind::Delete,
+        (true, false) => DiffKind::Insert,
+        (false, false) => DiffKind::Replace,
+    };
+    ops.push(DiffOp::new(kind, l, r));
+}
+
+/// Myers' O(ND) shortest edit script. Returns `false` if the edit distance exceeds the cap.
+fn myers(
+    left: &[u8],
+    right: &[u8],
+    l: Range<usize>,
+    r: Range<usize>,
+    ops: &mut Vec<DiffOp>,
+) -> bool {
+    let a = &left[l.clone()];
+    let b = &right[r.clone()];
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max_d = (a.len() + b.len()).min(MYERS_MAX_EDITS) as isize;
+    let offset = max_d + 1;
+    let mut v = vec![0isize; 2 * offset as usize + 1];
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    let mut found = None;
+    'outer: for d in 0..=max_d {
+        trace.push(v.clone());
+        for k in (-d..=d).step_by(2) {
+            let idx = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[idx - 1] < v[idx + 1]) {
+                v[idx + 1]
+            } else {
+                v[idx - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[idx] = x;
+            if x >= n && y >= m {
+                found = Some(d);
+                break 'outer;
+            }
+        }
+    }
+    let Some(d_final) = found else {
+        return false;
+    };
+
+    // Walk the trace backwards, collecting single-byte steps.
+    let mut steps = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for d in (1..=d_final).rev() {
+        let v = &trace[d as usize];
+        let k = x - y;
+        let prev_k =
+            if k == -d || (k != d && v[(k - 1 + offset) as usize] < v[(k + 1 + offset) as usize]) {
+                k + 1
+            } else {
+                k - 1
+            };
+        let prev_x = v[(prev_k + offset) as usize];
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            steps.push((DiffKind::Equal, x, y));
+        }
+        if x == prev_x {
+            y -= 1;
+            steps.push((DiffKind::Insert, x, y));
+        } else {
+            x -= 1;
+            steps.push((DiffKind::Delete, x, y));
+        }
+    }
+    while x > 0 && y > 0 {
+        x -= 1;
+        y -= 1;
+        steps.push((DiffKind::Equal, x, y));
+    }
+
+    for (kind, x, y) in steps.into_iter().rev() {
+        let (x, y) = (l.start + x as usize, r.start + y as usize);
+        let op = match kind {
+            DiffKind::Equal => DiffOp::new(kind, x..x + 1, y..y + 1),
+            DiffKind::Delete => DiffOp::new(kind, x..x + 1, y..y),
+            _ => DiffOp::new(kind, x..x, y..y + 1),
+        };
+        ops.push(op);
+    }
+    true
+}
+
+/// Find matching blocks shared by both regions, in increasing order on both sides.
+fn find_anchors(
+    left: &[u8],
+    right: &[u8],
+    l: Range<usize>,
+    r: Range<usize>,
+) -> Vec<(usize, usize, usize)> {
+    if l.len() < ANCHOR_BLOCK || r.len() < ANCHOR_BLOCK {
+        return Vec::new();
+    }
+
+    // Index non-overlapping blocks of the right side; ambiguous blocks are dropped so
+    // repeated padding (0x00/0xFF fills) cannot produce misleading anchors.
+    let mut index: HashMap<u64, Option<usize>> = HashMap::new();
+    let mut pos = r.start;
+    while pos + ANCHOR_BLOCK <= r.end {
+        let hash = RollingHash::new(&right[pos..pos + ANCHOR_BLOCK]).value();
+        index
+            .entry(hash)
+            .and_modify(|slot| *slot = None)
+            .or_insert(Some(pos));
+        pos += ANCHOR_BLOCK;
+    }
+
+    let mut anchors = Vec::new();
+    let mut min_right = r.start;
+    let mut x = l.start;
+    let mut hash = RollingHash::new(&left[x..x + ANCHOR_BLOCK]);
+    loop {
+        let candidate = index.get(&hash.value()).copied().flatten();
+        if let Some(ry) = candidate.filter(|&ry| ry >= min_right)
+            && left[x..x + ANCHOR_BLOCK] == right[ry..ry + ANCHOR_BLOCK]
+        {
+            let mut len = ANCHOR_BLOCK;
+            while x + len < l.end && ry + len < r.end && left[x + len] == right[ry + len] {
+                len += 1;
+            }
+            anchors.push((x, ry, len));
+            min_right = ry + len;
+            x += len;
+            if x + ANCHOR_BLOCK > l.end {
+                break;
+            }
+            hash = RollingHash::new(&left[x..x + ANCHOR_BLOCK]);
+            continue;
+        }
+        if x + ANCHOR_BLOCK >= l.end {
+            break;
+        }
+        hash.roll(left[x], left[x + ANCHOR_BLOCK]);
+        x += 1;
+    }
+    anchors
+}
+
+/// Polynomial rolling hash over a fixed window of `ANCHOR_BLOCK` bytes.
+struct RollingHash {
+    value: u64,
+    top: u64,
+}
+
+impl RollingHash {
+    const BASE: u64 = 257;
+
+    fn new(window: &[u8]) -> Self {
+        let mut value = 0u64;
+        let mut top = 1u64;
+        for (idx, &byte) in window.iter().enumerate() {
+            value = value.wrapping_mul(Self::BASE).wrapping_add(byte as u64);
+            if idx > 0 {
+                top = top.wrapping_mul(Self::BASE);
+            }
+        }
+        Self { value, top }
+    }
+
+    fn roll(&mut self, out: u8, incoming: u8) {
+        self.value = self
+            .value
+            .wrapping_sub((out as u64).wrapping_mul(self.top))
+            .wrapping_mul(Self::BASE)
+            .wrapping_add(incoming as u64);
+    }
+
+    fn value(&self) -> u64 {
+        self.value
+    }
+}
+
+fn common_prefix(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(x, y)| x == y).count()
+}
+
+fn common_suffix(a: &[u8], b: &[u8]) -> usize {
+    a.iter()
+        .rev()
+        .zip(b.iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count()
+}
+
+/// Merge adjacent ops and fold neighbouring deletes/inserts into replacements.
+fn normalize(ops: Vec<DiffOp>) -> Vec<DiffOp> {
+    let mut merged: Vec<DiffOp> = Vec::with_capacity(ops.len());
+    for op in ops {
+        if let Some(last) = merged.last_mut() {
+            let changed = last.kind != DiffKind::Equal && op.kind != DiffKind::Equal;
+            if (last.kind == op.kind || changed)
+                && last.left.end == op.left.start
+                && last.right.end == op.right.start
+            {
+                last.left.end = op.left.end;
+                last.right.end = op.right.end;
+                if changed && last.kind != op.kind {
+                    last.kind = DiffKind::Replace;
+                }
+                if last.kind == DiffKind::Replace {
+                    last.kind = match (last.left.is_empty(), last.right.is_empty()) {
+                        (false, true) => DiffKind::Delete,
+                        (true, false) => DiffKind::Insert,
+                        _ => DiffKind::Replace,
+                    };
+                }
+                continue;
+            }
+        }
+        merged.push(op);
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic noise, so anchors are unambiguous.
+    fn noise(len: usize, seed: u32) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                (state >> 16) as u8
+            })
+            .collect()
+    }
+
+    /// The ops must cover both buffers in order, and equal ops must really be equal.
+    fn assert_covers(ops: &[DiffOp], left: &[u8], right: &[u8]) {
+        let (mut l, mut r) = (0, 0);
+        for op in ops {
+            assert_eq!((op.left.start, op.right.start), (l, r), "gap before {op:?}");
+            match op.kind {
+                DiffKind::Equal => assert_eq!(left[op.left.clone()], right[op.right.clone()]),
+                DiffKind::Delete => assert!(op.right.is_empty() && !op.left.is_empty()),
+                DiffKind::Insert => assert!(op.left.is_empty() && !op.right.is_empty()),
+                DiffKind::Replace => assert!(!op.left.is_empty() && !op.right.is_empty()),
+            }
+            (l, r) = (op.left.end, op.right.end);
+        }
+        assert_eq!((l, r), (left.len(), right.len()));
+    }
+
+    #[test]
+    fn compare_reports_runs_and_length_difference() {
+        let ops = compare(b"abcdef", b"abXdefgh");
+        assert_eq!(
+            ops,
+            vec![
+                DiffOp::new(DiffKind::Equal, 0..2, 0..2),
+                DiffOp::new(DiffKind::Replace, 2..3, 2..3),
+                DiffOp::new(DiffKind::Equal, 3..6, 3..6),
+                DiffOp::new(DiffKind::Insert, 6..6, 6..8),
+            ]
+        );
+        assert!(compare(b"", b"").is_empty());
+    }
+
+    #[test]
+    fn align_lines_up_small_insertions_and_deletions() {
+        assert_eq!(
+            align(b"abcdef", b"abcXYdef"),
+            vec![
+                DiffOp::new(DiffKind::Equal, 0..3, 0..3),
+                DiffOp::new(DiffKind::Insert, 3..3, 3..5),
+                DiffOp::new(DiffKind::Equal, 3..6, 5..8),
+            ]
+        );
+        assert_eq!(
+            align(b"abcXYdef", b"abcdef"),
+            vec![
+                DiffOp::new(DiffKind::Equal, 0..3, 0..3),
+                DiffOp::new(DiffKind::Delete, 3..5, 3..3),
+                DiffOp::new(DiffKind::Equal, 5..8, 3..6),
+            ]
+        );
+    }
+
+    #[test]
+    fn align_handles_empty_and_unrelated_buffers() {
+        assert!(align(b"", b"").is_empty());
+        assert_eq!(
+            align(b"", b"abc"),
+            vec![DiffOp::new(DiffKind::Insert, 0..0, 0..3)]
+        );
+        assert_eq!(
+            align(b"abc", b""),
+            vec![DiffOp::new(DiffKind::Delete, 0..3, 0..0)]
+        );
+        let (left, right) = (noise(100, 1), noise(100, 2));
+        assert_covers(&align(&left, &right), &left, &right);
+    }
+
+    #[test]
+    fn align_uses_anchors_for_large_shifted_buffers() {
+        let left = noise(64 * 1024, 7);
+        let inserted = noise(100, 9);
+        let mut right = left[..20_000].to_vec();
+        right.extend_from_slice(&inserted);
+        right.extend_from_slice(&left[20_000..]);
+
+        let ops = align(&left, &right);
+        assert_covers(&ops, &left, &right);
+        let changed: Vec<&DiffOp> = ops.iter().filter(|op| op.kind != DiffKind::Equal).collect();
+        assert_eq!(
+            changed,
+            vec![&DiffOp::new(
+                DiffKind::Insert,
+                20_000..20_000,
+                20_000..20_100
+            )]
+        );
+    }
+
+    #[test]
+    fn align_falls_back_when_edits_exceed_the_myers_cap() {
+        let left = noise(4000, 3);
+        let right = noise(4000, 4);
+        let ops = align(&left, &right);
+        assert_covers(&ops, &left, &right);
+    }
+}
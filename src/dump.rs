@@ -0,0 +1,288 @@
+use std::fmt::Write as FmtWrite;
+
+use anyhow::{Context, Result, bail};
+
+use crate::{
+    app::{Grouping, format_line, printable},
+    args::{Base, DumpStyle},
+};
+
+/// Default bytes per line for `xxd -p` style output.
+pub const PLAIN_WIDTH: usize = 30;
+
+/// Render `bytes` (starting at file offset `base`) in the given style, one line per row.
+pub fn format_dump(style: DumpStyle, base: usize, bytes: &[u8], width: usize) -> String {
+    let mut out = String::new();
+    let mut previous: Option<&[u8]> = None;
+    let mut squeezing = false;
+    for (row, chunk) in bytes.chunks(width).enumerate() {
+        let offset = base + row * width;
+        let line = match style {
+            DumpStyle::Default => format_line(offset, chunk, width, Grouping::default(), Base::Hex),
+            DumpStyle::Xxd => format_xxd(offset, chunk, width),
+            DumpStyle::Canonical => {
+                // Like `hexdump -C`, collapse runs of identical full rows into a `*`.
+                if chunk.len() == width && previous == Some(chunk) {
+                    if !squeezing {
+                        out.push_str("*\n");
+                        squeezing = true;
+                    }
+                    continue;
+                }
+                squeezing = false;
+                previous = Some(chunk);
+                format_canonical(offset, chunk, width)
+            }
+            DumpStyle::Plain => chunk.iter().fold(String::new(), |mut line, byte| {
+                let _ = write!(line, "{byte:02x}");
+                line
+            }),
+        };
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    if style == DumpStyle::Canonical {
+        let _ = writeln!(out, "{:08x}", base + bytes.len());
+    }
+    out
+}
+
+fn format_xxd(offset: usize, chunk: &[u8], width: usize) -> String {
+    let mut hex = String::with_capacity(width * 5 / 2);
+    for idx in 0..width {
+        if idx > 0 && idx % 2 == 0 {
+            hex.push(' ');
+        }
+        match chunk.get(idx) {
+            Some(byte) => {
+                let _ = write!(hex, "{byte:02x}");
+            }
+            None => hex.push_str("  "),
+        }
+    }
+    let ascii: String = chunk.iter().map(|&byte| printable(byte)).collect();
+    format!("{offset:08x}: {hex}  {ascii}")
+}
+
+fn format_canonical(offset: usize, chunk: &[u8], width: usize) -> String {
+    let mut hex = String::with_capacity(width * 3 + 1);
+    for idx in 0..width {
+        if idx == width / 2 {
+            hex.push(' ');
+        }
+        match chunk.get(idx) {
+            Some(byte) => {
+                let _ = write!(hex, "{byte:02x} ");
+            }
+            None => hex.push_str("   "),
+        }
+    }
+    let ascii: String = chunk.iter().map(|&byte| printable(byte)).collect();
+    format!("{offset:08x}  {hex} |{ascii}|")
+}
+
+/// Parse a dump in any of the supported styles back into bytes.
+///
+/// Without a `style`, input made only of hex digits is read as plain hex and anything
+/// else as rows with offsets. Offsets in the dump are honoured, so gaps between rows are
+/// zero-filled; with `relative` the first offset is treated as the start of the output.
+pub fn parse_dump(text: &str, style: Option<DumpStyle>, relative: bool) -> Result<Vec<u8>> {
+    let lines: Vec<&str> = text
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    // `hexdump -C` of an empty input is just the closing offset line, which is also a
+    // valid `xxd -p` dump of four bytes; only an explicit style reads it as empty.
+    let is_plain = match style {
+        Some(style) => style == DumpStyle::Plain,
+        None => lines.iter().all(|line| {
+            line.chars()
+                .all(|c| c.is_ascii_hexdigit() || c.is_whitespace())
+        }),
+    };
+    if is_plain {
+        let digits: String = lines.concat().split_whitespace().collect();
+        return hex_pairs(&digits).context("Invalid plain hex dump");
+    }
+
+    let mut out: Vec<u8> = Vec::new();
+    let mut base: Option<usize> = None;
+    let mut repeat: Option<Vec<u8>> = None;
+    let mut last_row: Vec<u8> = Vec::new();
+    for (idx, line) in lines.iter().enumerate() {
+        let line_no = idx + 1;
+        if line.trim() == "*" {
+            repeat = Some(last_row.clone());
+            continue;
+        }
+        let (offset, bytes) =
+            parse_row(line).with_context(|| format!("Invalid dump line {line_no}: {line:?}"))?;
+        let base = *base.get_or_insert(if relative { offset } else { 0 });
+        let Some(pos) = offset.checked_sub(base) else {
+            bail!("Dump line {line_no} has an offset before the start of the dump");
+        };
+        if let Some(row) = repeat.take().filter(|row| !row.is_empty()) {
+            while out.len() < pos {
+                let take = row.len().min(pos - out.len());
+                out.extend_from_slice(&row[..take]);
+            }
+        }
+        // A bare offset line only closes the dump; it must not pad past the data.
+        if bytes.is_empty() {
+            continue;
+        }
+        if out.len() < pos {
+            out.resize(pos, 0);
+        }
+        let end = pos + bytes.len();
+        if out.len() < end {
+            out.resize(end, 0);
+        }
+        out[pos..end].copy_from_slice(&bytes);
+        last_row = bytes;
+    }
+    Ok(out)
+}
+
+/// Split a dump row into its offset and data bytes, ignoring the ASCII column.
+fn parse_row(line: &str) -> Result<(usize, Vec<u8>)> {
+    let line = line.trim_start();
+    let offset_end = line
+        .find(|c: char| !c.is_ascii_hexdigit())
+        .unwrap_or(line.len());
+    let offset = usize::from_str_radix(&line[..offset_end], 16).context("missing offset")?;
+    let rest = &line[offset_end..];
+
+    // xxd separates its text column with a double space after the hex groups, which are
+    // themselves single-space separated; other styles delimit it with `|ascii|`, and the
+    // text itself may contain either.
+    let hex = match rest.strip_prefix(':') {
+        Some(body) => {
+            let body = body.trim_start();
+            body.find("  ").map_or(body, |pos| &body[..pos])
+        }
+        None => rest.find('|').map_or(rest, |pos| &rest[..pos]),
+    };
+    let digits: String = hex.split_whitespace().collect();
+    Ok((offset, hex_pairs(&digits)?))
+}
+
+fn hex_pairs(digits: &str) -> Result<Vec<u8>> {
+    if !digits.is_ascii() {
+        bail!("invalid hex digits");
+    }
+    if !digits.len().is_multiple_of(2) {
+        bail!("odd number of hex digits");
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|idx| {
+            u8::from_str_radix(&digits[idx..idx + 2], 16)
+                .with_context(|| format!("invalid hex byte {:?}", &digits[idx..idx + 2]))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STYLES: [DumpStyle; 4] = [
+        DumpStyle::Default,
+        DumpStyle::Xxd,
+        DumpStyle::Canonical,
+        DumpStyle::Plain,
+    ];
+
+    fn sample() -> Vec<u8> {
+        let mut bytes: Vec<u8> = (0..=255).collect();
+        bytes.extend_from_slice(b"  hello |world|  ");
+        bytes.extend(std::iter::repeat_n(0, 64));
+        bytes.extend_from_slice(b"tail");
+        bytes
+    }
+
+    #[test]
+    fn every_style_round_trips() {
+        let bytes = sample();
+        for style in STYLES {
+            let text = format_dump(style, 0, &bytes, 16);
+            assert_eq!(parse_dump(&text, None, false).unwrap(), bytes, "{style:?}");
+            assert_eq!(
+                parse_dump(&text, Some(style), false).unwrap(),
+                bytes,
+                "{style:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn offsets_are_kept_unless_relative() {
+        let text = format_dump(DumpStyle::Xxd, 0x20, b"abc", 16);
+        let mut padded = vec![0; 0x20];
+        padded.extend_from_slice(b"abc");
+        assert_eq!(parse_dump(&text, None, false).unwrap(), padded);
+        assert_eq!(parse_dump(&text, None, true).unwrap(), b"abc");
+    }
+
+    #[test]
+    fn canonical_squeezes_repeated_rows() {
+        let bytes = [7; 80];
+        let text = format_dump(DumpStyle::Canonical, 0, &bytes, 16);
+        assert_eq!(text.matches('*').count(), 1);
+        assert_eq!(parse_dump(&text, None, false).unwrap(), bytes);
+    }
+
+    #[test]
+    fn empty_dumps_are_empty() {
+        for style in STYLES {
+            let text = format_dump(style, 0, &[], 16);
+            assert_eq!(
+                parse_dump(&text, Some(style), false).unwrap(),
+                b"",
+                "{style:?}"
+            );
+        }
+        let canonical = Some(DumpStyle::Canonical);
+        assert_eq!(parse_dump("00000000\n", canonical, false).unwrap(), b"");
+        assert_eq!(parse_dump("0000000a\n", canonical, true).unwrap(), b"");
+        assert_eq!(parse_dump("deadbeef\n", canonical, false).unwrap(), b"");
+    }
+
+    #[test]
+    fn four_byte_plain_dumps_round_trip() {
+        for bytes in [[0xDE, 0xAD, 0xBE, 0xEF], [0; 4]] {
+            let text = format_dump(DumpStyle::Plain, 0, &bytes, PLAIN_WIDTH);
+            assert_eq!(text.trim_end().len(), 8);
+            assert_eq!(parse_dump(&text, None, false).unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn trailing_offsets_do_not_pad() {
+        let text = "00000000  41 42 |AB|\n000000ff\n";
+        assert_eq!(parse_dump(text, None, false).unwrap(), b"AB");
+    }
+
+    #[test]
+    fn plain_dumps_ignore_whitespace() {
+        assert_eq!(
+            parse_dump("de ad\nbe ef\n", None, false).unwrap(),
+            [0xDE, 0xAD, 0xBE, 0xEF]
+        );
+        assert_eq!(
+            parse_dump("0011\n2233\n", None, false).unwrap(),
+            [0, 0x11, 0x22, 0x33]
+        );
+        assert!(parse_dump("abc\n", None, false).is_err());
+    }
+
+    #[test]
+    fn bad_lines_are_reported() {
+        let error = parse_dump("00000000: zz  ..\n", None, false).unwrap_err();
+        assert!(format!("{error:#}").contains("line 1"));
+        let error = parse_dump("00000010  00 |.|\n00000000  00 |.|\n", None, true).unwrap_err();
+        assert!(error.to_string().contains("before the start"));
+    }
+}
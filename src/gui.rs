@@ -1,18 +1,41 @@
-use anyhow::{Result, anyhow};
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Result, anyhow, bail};
+use clap::ValueEnum;
 use eframe::{
     App as EguiApp, Frame, NativeOptions,
-    egui::{self, Align, Event, Key, RichText, ScrollArea, SelectableLabel, TopBottomPanel, vec2},
+    egui::{
+        self, Align, Align2, Color32, Event, Key, Modifiers, RichText, ScrollArea, SelectableLabel,
+        SidePanel, TextEdit, TopBottomPanel, vec2,
+    },
 };
 
-use crate::app::{App, RowText};
+use crate::{
+    app::{App, DISK_CHECK_INTERVAL, GROUP_SIZES, Grouping, RowText, Split},
+    args::Base,
+    cmdline::{COMMANDS, Command, Outcome, fuzzy_score, takes_arguments},
+    keymap::{ACTIONS, Action, KeyChord, Keymap},
+    recent::RecentFiles,
+    tabs::Tabs,
+    template::Field,
+    theme::{Rgb, Theme},
+};
 
 const GUI_ROW_HEIGHT: f32 = 20.0;
+const STREAM_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Loads a file chosen in the GUI into a buffer for a new tab.
+pub type Opener = Box<dyn Fn(&Path) -> Result<App>>;
 
 /// Launch the egui-based GUI frontend.
-pub fn run(app: App, debug: bool) -> Result<()> {
+pub fn run(tabs: Tabs<App>, keymap: Keymap, debug: bool, open: Opener) -> Result<()> {
     let options = NativeOptions {
         viewport: egui::ViewportBuilder::default()
-            .with_title("Rust Hex Viewer")
+            .with_title(window_title(tabs.active()))
             .with_inner_size([1000.0, 700.0])
             .with_min_inner_size([600.0, 400.0]),
         ..Default::default()
@@ -21,16 +44,63 @@ pub fn run(app: App, debug: bool) -> Result<()> {
     eframe::run_native(
         "Rust Hex Viewer",
         options,
-        Box::new(move |_cc| Box::new(HexGui::new(app, debug))),
+        Box::new(move |cc| {
+            if let Some(theme) = tabs.active().theme() {
+                apply_visuals(&cc.egui_ctx, theme);
+            }
+            Box::new(HexGui::new(tabs, keymap, debug, open))
+        }),
     )
     .map_err(|e| anyhow!("Failed to run eframe: {e}"))
 }
 
 struct HexGui {
+    tabs: Tabs<HexTab>,
+    title: String,
+    keymap: Keymap,
+    /// Keys typed so far of a longer binding, like the first `g` of `g t`.
+    pending_keys: Vec<KeyChord>,
+    /// Selection before a pending key ran its own action, so a longer binding can undo it.
+    cursor_before: Option<usize>,
+    palette: Palette,
+    help_open: bool,
+    /// Asking whether to quit and drop unsaved edits.
+    confirm_quit: bool,
+    /// Closing was confirmed, so the window may close despite unsaved edits.
+    quitting: bool,
+    open: Opener,
+    recent: RecentFiles,
+    debug: bool,
+}
+
+/// Menu entries that need more than the active tab.
+enum MenuCommand {
+    Action(Action),
+    /// Open the palette with this command typed in.
+    Palette(&'static str),
+    OpenRecent(PathBuf),
+    ClearRecent,
+    ExportPatch,
+}
+
+/// One open file with its own selection and scroll position.
+struct HexTab {
     app: App,
-    selected_offset: Option<usize>,
     debug: bool,
     last_vertical_move: Option<VerticalMove>,
+    /// Whether typed hex digits overwrite the selected byte.
+    editing: bool,
+    /// High nibble typed for the selected byte, waiting for the low nibble.
+    pending_nibble: Option<u8>,
+    /// Scroll offset of each pane after the last frame.
+    pane_offsets: Vec<f32>,
+    /// Offsets last seen by the scroll lock, to tell which pane the user scrolled.
+    locked_offsets: Vec<f32>,
+    /// Offsets to apply to panes on the next frame.
+    scroll_requests: Vec<Option<f32>>,
+    reveal_selection: bool,
+    /// Byte to scroll to on the next frame, however far away it is.
+    pending_jump: Option<usize>,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -40,23 +110,578 @@ enum VerticalMove {
 }
 
 impl HexGui {
-    fn new(app: App, debug: bool) -> Self {
-        let initial_selection = if app.bytes_len() > 0 { Some(0) } else { None };
+    fn new(tabs: Tabs<App>, keymap: Keymap, debug: bool, open: Opener) -> Self {
+        let title = window_title(tabs.active());
+        let tabs = tabs
+            .into_iter()
+            .map(|app| HexTab::new(app, debug))
+            .collect();
+
+        let palette = Palette::new(&keymap);
+        Self {
+            tabs: Tabs::new(tabs),
+            title,
+            keymap,
+            pending_keys: Vec::new(),
+            cursor_before: None,
+            palette,
+            help_open: false,
+            confirm_quit: false,
+            quitting: false,
+            open,
+            recent: RecentFiles::load(),
+            debug,
+        }
+    }
+
+    /// Open `path` in a new tab, or switch to it if it is already open. An empty
+    /// "untitled" tab is replaced.
+    fn open_file(&mut self, path: &Path) {
+        let canonical = path.canonicalize().ok();
+        let existing = self.tabs.iter().position(|tab| {
+            tab.app
+                .path()
+                .is_some_and(|open| open == path || open.canonicalize().ok() == canonical)
+        });
+        if let Some(index) = existing {
+            self.tabs.select(index);
+            return;
+        }
+        let app = match (self.open)(path) {
+            Ok(app) => app,
+            Err(err) => {
+                self.tabs
+                    .active_mut()
+                    .app
+                    .set_message(format!("open failed: {err:#}"));
+                return;
+            }
+        };
+        let tab = HexTab::new(app, self.debug);
+        let active = &self.tabs.active().app;
+        if active.path().is_none() && active.bytes_len() == 0 && !active.is_streaming() {
+            *self.tabs.active_mut() = tab;
+        } else {
+            self.tabs.push(tab);
+        }
+        if let Err(err) = self.recent.add(path) {
+            self.tabs.active_mut().app.set_message(format!("{err:#}"));
+        }
+    }
+
+    fn pick_files(&mut self) {
+        let mut dialog = rfd::FileDialog::new();
+        if let Some(dir) = self.tabs.active().app.path().and_then(Path::parent) {
+            dialog = dialog.set_directory(dir);
+        }
+        for path in dialog.pick_files().unwrap_or_default() {
+            self.open_file(&path);
+        }
+    }
+
+    fn export_patch(&mut self) {
+        let app = &mut self.tabs.active_mut().app;
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("IPS patch", &["ips"])
+            .add_filter("BPS patch", &["bps"])
+            .add_filter("Text patch", &["txt"])
+            .save_file()
+        else {
+            return;
+        };
+        match app.export_patch(&path) {
+            Ok(count) => app.set_message(format!("exported {count} edits to {}", path.display())),
+            Err(err) => app.set_message(format!("export failed: {err:#}")),
+        }
+    }
+
+    /// Open files dropped onto the window, and say what will happen while they hover.
+    fn handle_dropped_files(&mut self, ctx: &egui::Context) {
+        let (hovering, dropped): (bool, Vec<PathBuf>) = ctx.input(|input| {
+            (
+                !input.raw.hovered_files.is_empty(),
+                input
+                    .raw
+                    .dropped_files
+                    .iter()
+                    .filter_map(|file| file.path.clone())
+                    .collect(),
+            )
+        });
+        for path in dropped {
+            self.open_file(&path);
+        }
+        if hovering {
+            let screen = ctx.screen_rect();
+            let painter = ctx.layer_painter(egui::LayerId::new(
+                egui::Order::Foreground,
+                egui::Id::new("drop_target"),
+            ));
+            painter.rect_filled(screen, 0.0, Color32::from_black_alpha(160));
+            painter.text(
+                screen.center(),
+                Align2::CENTER_CENTER,
+                "Drop files to open them",
+                egui::FontId::proportional(24.0),
+                Color32::WHITE,
+            );
+        }
+    }
+
+    fn draw_menu_bar(&mut self, ctx: &egui::Context) {
+        let mut command = None;
+        let keymap = &self.keymap;
+        let app = &mut self.tabs.active_mut().app;
+        let recent = self.recent.paths();
+        TopBottomPanel::top("menu_bar").show(ctx, |ui| {
+            egui::menu::bar(ui, |ui| {
+                ui.menu_button("File", |ui| {
+                    action_item(ui, keymap, "Open...", Action::Open, &mut command);
+                    ui.add_enabled_ui(!recent.is_empty(), |ui| {
+                        ui.menu_button("Open Recent", |ui| {
+                            for path in recent {
+                                if ui.button(path.display().to_string()).clicked() {
+                                    command = Some(MenuCommand::OpenRecent(path.clone()));
+                                    ui.close_menu();
+                                }
+                            }
+                            ui.separator();
+                            if ui.button("Clear List").clicked() {
+                                command = Some(MenuCommand::ClearRecent);
+                                ui.close_menu();
+                            }
+                        });
+                    });
+                    ui.separator();
+                    action_item(ui, keymap, "Save", Action::Save, &mut command);
+                    action_item(ui, keymap, "Reload", Action::Reload, &mut command);
+                    ui.separator();
+                    action_item(ui, keymap, "Quit", Action::Quit, &mut command);
+                });
+                ui.menu_button("Edit", |ui| {
+                    action_item(
+                        ui,
+                        keymap,
+                        "Overwrite Bytes",
+                        Action::ToggleEdit,
+                        &mut command,
+                    );
+                    if ui.button("Fill...").clicked() {
+                        command = Some(MenuCommand::Palette("fill "));
+                        ui.close_menu();
+                    }
+                });
+                ui.menu_button("View", |ui| {
+                    let split = app.split();
+                    if ui
+                        .radio(split == Some(Split::Horizontal), "Split Horizontally")
+                        .clicked()
+                    {
+                        app.toggle_split(Split::Horizontal);
+                        ui.close_menu();
+                    }
+                    if ui
+                        .radio(split == Some(Split::Vertical), "Split Vertically")
+                        .clicked()
+                    {
+                        app.toggle_split(Split::Vertical);
+                        ui.close_menu();
+                    }
+                    let mut locked = app.is_scroll_locked();
+                    if ui
+                        .add_enabled(
+                            split.is_some(),
+                            egui::Checkbox::new(&mut locked, "Lock Scroll"),
+                        )
+                        .clicked()
+                    {
+                        app.toggle_scroll_lock();
+                    }
+                    action_item(ui, keymap, "Next Pane", Action::NextPane, &mut command);
+                    ui.separator();
+                    ui.menu_button("Base", |ui| {
+                        for &base in Base::value_variants() {
+                            if ui.radio(app.base() == base, base.name()).clicked() {
+                                app.set_base(base);
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                    ui.menu_button("Group Size", |ui| {
+                        let grouping = app.grouping();
+                        for size in GROUP_SIZES {
+                            if ui.radio(grouping.size == size, size.to_string()).clicked() {
+                                app.set_grouping(Grouping { size, ..grouping });
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                    let mut grouping = app.grouping();
+                    if ui
+                        .checkbox(&mut grouping.little_endian, "Little-endian")
+                        .clicked()
+                    {
+                        app.set_grouping(grouping);
+                    }
+                    let mut status_bar = app.is_status_bar_visible();
+                    if ui.checkbox(&mut status_bar, "Status Bar").clicked() {
+                        app.toggle_status_bar();
+                    }
+                });
+                ui.menu_button("Search", |ui| {
+                    if ui.button("Go to Offset...").clicked() {
+                        command = Some(MenuCommand::Palette("goto "));
+                        ui.close_menu();
+                    }
+                    if ui.button("Find...").clicked() {
+                        command = Some(MenuCommand::Palette("find "));
+                        ui.close_menu();
+                    }
+                });
+                ui.menu_button("Help", |ui| {
+                    action_item(ui, keymap, "Key Bindings", Action::Help, &mut command);
+                });
+                ui.menu_button("Tools", |ui| {
+                    action_item(
+                        ui,
+                        keymap,
+                        "Command Palette",
+                        Action::CommandLine,
+                        &mut command,
+                    );
+                    if ui.button("Export Patch...").clicked() {
+                        command = Some(MenuCommand::ExportPatch);
+                        ui.close_menu();
+                    }
+                });
+            });
+        });
+        match command {
+            Some(MenuCommand::Action(action)) => self.run_action(ctx, action),
+            Some(MenuCommand::Palette(query)) => self.palette.open_with(query),
+            Some(MenuCommand::OpenRecent(path)) => self.open_file(&path),
+            Some(MenuCommand::ClearRecent) => {
+                if let Err(err) = self.recent.clear() {
+                    self.tabs.active_mut().app.set_message(format!("{err:#}"));
+                }
+            }
+            Some(MenuCommand::ExportPatch) => self.export_patch(),
+            None => {}
+        }
+    }
+
+    /// Run the actions bound to the keys pressed this frame. While the palette is open
+    /// only its own shortcut gets through, so typing there does not trigger anything.
+    /// Any other key cancels a pending "press again" confirmation.
+    fn handle_keys(&mut self, ctx: &egui::Context) {
+        let chords: Vec<KeyChord> = ctx.input(|input| {
+            input
+                .events
+                .iter()
+                .filter_map(|event| match event {
+                    Event::Key {
+                        key,
+                        pressed: true,
+                        modifiers,
+                        ..
+                    } => key_chord(*key, *modifiers),
+                    _ => None,
+                })
+                .collect()
+        });
+        for chord in chords {
+            if self.palette.open {
+                self.pending_keys.clear();
+                if self.keymap.lookup(&[chord]).action == Some(Action::CommandLine) {
+                    self.run_action(ctx, Action::CommandLine);
+                }
+                continue;
+            }
+            let (lookup, len) = self.keymap.press(&mut self.pending_keys, chord);
+            let cursor_before = self.cursor_before.take();
+            if !lookup.is_prefix && !lookup.action.is_some_and(Action::keeps_confirm) {
+                self.tabs.active_mut().app.cancel_confirm();
+            }
+            if let Some(action) = lookup.action {
+                if len > 1
+                    && let Some(cursor) = cursor_before
+                {
+                    self.tabs.active_mut().jump_to(cursor);
+                }
+                let cursor = self.tabs.active().app.cursor();
+                self.run_action(ctx, action);
+                if lookup.is_prefix {
+                    self.cursor_before = cursor;
+                }
+            }
+        }
+    }
+
+    fn run_action(&mut self, ctx: &egui::Context, action: Action) {
+        match action {
+            Action::Quit => self.request_quit(ctx),
+            Action::CommandLine => self.palette.toggle(),
+            Action::Help => self.help_open = !self.help_open,
+            Action::Open => self.pick_files(),
+            Action::NextTab => self.tabs.cycle(1),
+            Action::PrevTab => self.tabs.cycle(-1),
+            Action::SelectTab(index) => self.tabs.select(index),
+            action => self.tabs.active_mut().run_action(action),
+        }
+    }
+
+    /// Run a command line typed into the palette, as the TUI does after `:`.
+    fn run_command(&mut self, ctx: &egui::Context, line: &str) -> Result<()> {
+        let tab = self.tabs.active_mut();
+        match Command::parse(line)?.run(&mut tab.app)? {
+            Outcome::Done => {}
+            Outcome::Goto(index) => tab.jump_to(index),
+            Outcome::Quit { force } => {
+                if !force && self.is_modified() {
+                    bail!("No write since last change (add ! to override)");
+                }
+                self.quit(ctx);
+            }
+            Outcome::Action(action) => self.run_action(ctx, action),
+        }
+        Ok(())
+    }
+
+    fn is_modified(&self) -> bool {
+        self.tabs.iter().any(|tab| tab.app.is_modified())
+    }
 
+    /// Close the window, asking first if that would drop unsaved edits.
+    fn request_quit(&mut self, ctx: &egui::Context) {
+        if self.is_modified() {
+            self.confirm_quit = true;
+        } else {
+            self.quit(ctx);
+        }
+    }
+
+    fn quit(&mut self, ctx: &egui::Context) {
+        self.quitting = true;
+        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+    }
+
+    /// Ask whether to quit without saving; the window stays open unless confirmed.
+    fn draw_quit_confirm(&mut self, ctx: &egui::Context) {
+        let modified = self.tabs.iter().filter(|tab| tab.app.is_modified()).count();
+        let mut quit = false;
+        egui::Window::new("Unsaved changes")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(format!("{modified} file(s) have unsaved edits."));
+                ui.horizontal(|ui| {
+                    quit = ui.button("Quit Without Saving").clicked();
+                    if ui.button("Cancel").clicked() {
+                        self.confirm_quit = false;
+                    }
+                });
+            });
+        if quit {
+            self.confirm_quit = false;
+            self.quit(ctx);
+        }
+    }
+
+    /// Window listing the key bindings, built from the keymap so it stays accurate.
+    fn draw_help(&mut self, ctx: &egui::Context) {
+        let entries = self.keymap.help();
+        egui::Window::new("Key bindings")
+            .open(&mut self.help_open)
+            .collapsible(false)
+            .default_width(480.0)
+            .show(ctx, |ui| {
+                ui.label(
+                    "Arrow keys move the cursor. With editing on, hex digits overwrite the byte under it.",
+                );
+                ui.separator();
+                ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    egui::Grid::new("key_bindings")
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for (keys, name, description) in entries {
+                                ui.label(RichText::new(keys).monospace());
+                                ui.label(description);
+                                ui.weak(name);
+                                ui.end_row();
+                            }
+                        });
+                });
+            });
+    }
+
+    fn draw_palette(&mut self, ctx: &egui::Context) {
+        match self.palette.show(ctx) {
+            Some(PaletteChoice::Action(action)) => {
+                self.palette.close();
+                self.run_action(ctx, action);
+            }
+            Some(PaletteChoice::Command(line)) => match self.run_command(ctx, &line) {
+                Ok(()) => self.palette.close(),
+                Err(err) => self.palette.error = Some(format!("{err:#}")),
+            },
+            None => {}
+        }
+    }
+
+    fn draw_tab_bar(&mut self, ctx: &egui::Context) {
+        if self.tabs.len() < 2 {
+            return;
+        }
+        let mut clicked = None;
+        TopBottomPanel::top("tab_bar").show(ctx, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for (idx, tab) in self.tabs.iter().enumerate() {
+                    let selected = idx == self.tabs.active_index();
+                    if ui
+                        .add(SelectableLabel::new(selected, tab.app.title()))
+                        .clicked()
+                    {
+                        clicked = Some(idx);
+                    }
+                }
+            });
+        });
+        if let Some(idx) = clicked {
+            self.tabs.select(idx);
+        }
+    }
+}
+
+impl HexTab {
+    fn new(app: App, debug: bool) -> Self {
         Self {
             app,
-            selected_offset: initial_selection,
             debug,
             last_vertical_move: None,
+            editing: false,
+            pending_nibble: None,
+            pane_offsets: Vec::new(),
+            locked_offsets: Vec::new(),
+            scroll_requests: Vec::new(),
+            reveal_selection: false,
+            pending_jump: None,
+        }
+    }
+
+    /// Select byte `index` and scroll the focused pane to it.
+    fn jump_to(&mut self, index: usize) {
+        if index < self.app.bytes_len() {
+            self.select(index);
+            self.pending_jump = Some(index);
+        }
+    }
+
+    fn run_action(&mut self, action: Action) {
+        let stride = self.app.bytes_per_row() as isize;
+        let page = stride * self.app.view_rows().max(1) as isize;
+        let current = self.app.cursor().unwrap_or(0) as isize;
+        let last = self.app.bytes_len().saturating_sub(1) as isize;
+        let mut jump = |delta: isize| self.jump_to((current + delta).clamp(0, last) as usize);
+        match action {
+            Action::CursorLeft => jump(-1),
+            Action::CursorRight => jump(1),
+            Action::CursorDown | Action::ScrollDown => jump(stride),
+            Action::CursorUp | Action::ScrollUp => jump(-stride),
+            Action::PageDown => jump(page),
+            Action::PageUp => jump(-page),
+            Action::GotoStart => jump(-current),
+            Action::GotoEnd => jump(last - current),
+            Action::Save => {
+                if let Err(err) = self.app.save() {
+                    self.app.set_message(format!("save failed: {err:#}"));
+                }
+            }
+            Action::ToggleEdit => self.toggle_editing(),
+            Action::Reload => self.reload(),
+            Action::SplitHorizontal => self.app.toggle_split(Split::Horizontal),
+            Action::SplitVertical => self.app.toggle_split(Split::Vertical),
+            Action::NextPane => self.app.focus_next_pane(),
+            Action::ToggleScrollLock => self.app.toggle_scroll_lock(),
+            Action::CycleBase => self.app.set_base(self.app.base().next()),
+            Action::CycleGroup => self.app.set_grouping(self.app.grouping().next_size()),
+            Action::ToggleLittleEndian => {
+                let grouping = self.app.grouping();
+                self.app.set_grouping(Grouping {
+                    little_endian: !grouping.little_endian,
+                    ..grouping
+                });
+            }
+            Action::ToggleStatusBar => self.app.toggle_status_bar(),
+            Action::Quit
+            | Action::CommandLine
+            | Action::Help
+            | Action::Open
+            | Action::NextTab
+            | Action::PrevTab
+            | Action::SelectTab(_) => {}
         }
     }
 
     fn is_selected(&self, offset: usize) -> bool {
-        self.selected_offset == Some(offset)
+        self.app.cursor() == Some(offset)
     }
 
     fn select(&mut self, offset: usize) {
-        self.selected_offset = Some(offset);
+        self.app.set_cursor(offset);
+        self.pending_nibble = None;
+    }
+
+    fn toggle_editing(&mut self) {
+        self.pending_nibble = None;
+        if self.app.is_read_only() {
+            self.editing = false;
+            self.app
+                .set_message("read-only: editing is disabled".to_string());
+        } else {
+            self.editing = !self.editing;
+        }
+    }
+
+    /// Overwrite the selected byte with typed hex digits while editing is on.
+    fn handle_editing(&mut self, ctx: &egui::Context) {
+        if !self.editing {
+            return;
+        }
+        let mut digits: Vec<u8> = Vec::new();
+        ctx.input(|input| {
+            for event in &input.events {
+                if let Event::Text(text) = event {
+                    digits.extend(text.chars().filter_map(|c| c.to_digit(16)).map(|d| d as u8));
+                }
+            }
+        });
+
+        for digit in digits {
+            let Some(offset) = self.app.cursor() else {
+                break;
+            };
+            let Some(current) = self.app.byte_at(offset) else {
+                break;
+            };
+            let (value, complete) = match self.pending_nibble.take() {
+                Some(high) => ((high << 4) | digit, true),
+                None => ((digit << 4) | (current & 0x0F), false),
+            };
+            if !self.app.set_byte(offset, value) {
+                break;
+            }
+            if complete {
+                self.move_selection_by(1);
+            } else {
+                self.pending_nibble = Some(digit);
+            }
+        }
+    }
+
+    fn reload(&mut self) {
+        self.app.request_reload();
+        self.pending_nibble = None;
     }
 
     fn log_scroll(&self, direction: &str, row_offset: usize, rect_edge: f32, clip_edge: f32) {
@@ -108,19 +733,12 @@ impl HexGui {
     }
 
     fn move_selection_by(&mut self, delta: isize) {
-        let len = self.app.bytes_len();
-        if len == 0 {
-            self.selected_offset = None;
-            return;
-        }
-        let max_index = len.saturating_sub(1) as isize;
-        let current = self.selected_offset.unwrap_or(0) as isize;
-        let next = (current + delta).clamp(0, max_index);
-        self.selected_offset = Some(next as usize);
+        self.app.move_cursor(delta);
+        self.pending_nibble = None;
     }
 
     fn row_contains_selected(&self, row: &RowText) -> bool {
-        if let Some(sel) = self.selected_offset {
+        if let Some(sel) = self.app.cursor() {
             let start = row.offset;
             let end = row.offset + row.bytes.len();
             sel >= start && sel < end
@@ -132,151 +750,775 @@ impl HexGui {
 
 impl EguiApp for HexGui {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut Frame) {
-        let keyboard_moved = self.handle_keyboard_navigation(ctx);
+        if ctx.input(|input| input.viewport().close_requested())
+            && !self.quitting
+            && self.is_modified()
+        {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            self.confirm_quit = true;
+        }
+        for tab in self.tabs.iter_mut() {
+            tab.app.poll_incoming();
+        }
+        if self
+            .tabs
+            .iter()
+            .any(|tab| tab.app.is_streaming() || tab.app.is_following())
+        {
+            ctx.request_repaint_after(STREAM_POLL_INTERVAL);
+        } else if self.tabs.iter().any(|tab| tab.app.path().is_some()) {
+            ctx.request_repaint_after(DISK_CHECK_INTERVAL);
+        }
 
-        let mut selection_update: Option<usize> = None;
+        self.handle_keys(ctx);
+        self.handle_dropped_files(ctx);
+        self.draw_menu_bar(ctx);
+        self.draw_tab_bar(ctx);
+        if self.palette.open {
+            self.draw_palette(ctx);
+        }
+        if self.help_open {
+            self.draw_help(ctx);
+        }
+        if self.confirm_quit {
+            self.draw_quit_confirm(ctx);
+        }
+        let tab_index = self.tabs.active_index();
+        let accept_input = !self.palette.open;
+        self.tabs.active_mut().show(ctx, tab_index, accept_input);
+
+        let title = window_title(&self.tabs.active().app);
+        if title != self.title {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.clone()));
+            self.title = title;
+        }
+    }
+}
+
+impl HexTab {
+    /// Draw one pane of the hex view. Returns the byte clicked in it, if any.
+    fn draw_pane(
+        &mut self,
+        ui: &mut egui::Ui,
+        tab_index: usize,
+        pane: usize,
+        keyboard_moved: bool,
+    ) -> Option<usize> {
+        let mut clicked: Option<usize> = None;
+        // Only the focused pane follows the keyboard selection.
+        let scroll_to_selection = keyboard_moved && pane == self.app.focused_pane();
         let total_rows = self.app.total_rows();
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.add_space(4.0);
-            if total_rows == 0 {
-                ScrollArea::vertical()
-                    .auto_shrink([false, false])
-                    .show(ui, |ui| {
-                        ui.label("File is empty.");
-                    });
-                return;
+        draw_header(
+            ui,
+            self.app.bytes_per_row(),
+            self.app.grouping(),
+            self.app.base(),
+        );
+        ui.add_space(2.0);
+
+        let mut area = ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .id_source(("hex_scroll_area", tab_index, pane))
+            .stick_to_bottom(self.app.is_following());
+        if let Some(offset) = self.scroll_requests[pane].take() {
+            area = area.vertical_scroll_offset(offset);
+        }
+        let row_height = GUI_ROW_HEIGHT + ui.spacing().item_spacing.y;
+        let view_height = ui.available_height();
+        self.app
+            .set_view_rows(pane, (view_height / row_height).max(1.0) as usize);
+        if pane == self.app.focused_pane()
+            && let Some(index) = self.pending_jump.take()
+        {
+            // Center the row unless it is already in view.
+            let top = (index / self.app.bytes_per_row()) as f32 * row_height;
+            let current = self.pane_offsets[pane];
+            if top < current || top + row_height > current + view_height {
+                area = area.vertical_scroll_offset((top - view_height / 2.0).max(0.0));
             }
+        }
+        let output = area.show_rows(ui, GUI_ROW_HEIGHT, total_rows, |ui, row_range| {
+            let rows: Vec<RowText> = self.app.lines_for_range(row_range.start, row_range.len());
+            for row in rows {
+                let row_selected = scroll_to_selection && self.row_contains_selected(&row);
+                let row_response = ui.horizontal(|ui| {
+                    let offset = format!("{:08X}", self.app.display_offset(row.offset));
+                    ui.label(themed(
+                        &self.app,
+                        RichText::new(offset).monospace(),
+                        |theme| theme.offset,
+                    ));
+                    ui.add_space(4.0);
+                    ui.separator();
+                    ui.add_space(4.0);
+                    ui.scope(|ui| {
+                        let spacing = &mut ui.style_mut().spacing;
+                        spacing.item_spacing.x = 0.0;
+                        spacing.button_padding = vec2(2.0, 0.0);
 
-            draw_header(ui, self.app.bytes_per_row());
-            ui.add_space(2.0);
-
-            ScrollArea::vertical()
-                .auto_shrink([false, false])
-                .id_source("hex_scroll_area")
-                .show_rows(ui, GUI_ROW_HEIGHT, total_rows, |ui, row_range| {
-                    let rows: Vec<RowText> =
-                        self.app.lines_for_range(row_range.start, row_range.len());
-                    for row in rows {
-                        let row_selected = keyboard_moved && self.row_contains_selected(&row);
-                        let row_response = ui.horizontal(|ui| {
-                            ui.monospace(format!("{:08X}", row.offset));
-                            ui.add_space(4.0);
-                            ui.separator();
-                            ui.add_space(4.0);
-                            ui.scope(|ui| {
-                                let spacing = &mut ui.style_mut().spacing;
-                                spacing.item_spacing.x = 4.0;
-                                spacing.button_padding = vec2(2.0, 0.0);
-
-                                for (idx, byte) in row.bytes.iter().enumerate() {
-                                    if idx == self.app.bytes_per_row() / 2 {
-                                        ui.add_space(4.0);
-                                    }
-                                    let cell_offset = row.offset + idx;
-                                    let text = RichText::new(format!("{:02X}", byte)).monospace();
-                                    let response = ui.add(SelectableLabel::new(
-                                        self.is_selected(cell_offset),
-                                        text,
+                        let width = self.app.bytes_per_row();
+                        let base = self.app.base();
+                        let grouping = base.grouping(self.app.grouping());
+                        for (group_idx, group) in grouping.columns(width).iter().enumerate() {
+                            add_group_space(ui, grouping, group_idx, width);
+                            for &idx in group {
+                                let Some(&byte) = row.bytes.get(idx) else {
+                                    let blank = " ".repeat(base.cell_width());
+                                    ui.add(SelectableLabel::new(
+                                        false,
+                                        RichText::new(blank).monospace(),
                                     ));
-                                    if response.clicked() {
-                                        selection_update = Some(cell_offset);
-                                    }
+                                    continue;
+                                };
+                                let cell_offset = row.offset + idx;
+                                let text =
+                                    byte_text(&self.app, base.format_byte(byte), byte, cell_offset);
+                                let response = ui
+                                    .add(SelectableLabel::new(self.is_selected(cell_offset), text));
+                                if response.clicked() {
+                                    clicked = Some(cell_offset);
                                 }
-                            });
+                            }
+                        }
+                    });
 
-                            ui.add_space(6.0);
-                            ui.separator();
-                            ui.add_space(4.0);
-                            ui.scope(|ui| {
-                                let spacing = &mut ui.style_mut().spacing;
-                                spacing.item_spacing.x = 0.0;
-                                spacing.button_padding = vec2(2.0, 0.0);
-
-                                for (idx, byte) in row.bytes.iter().enumerate() {
-                                    let cell_offset = row.offset + idx;
-                                    let text = RichText::new(printable_ascii(*byte)).monospace();
-                                    let response = ui.add(SelectableLabel::new(
-                                        self.is_selected(cell_offset),
-                                        text,
-                                    ));
-                                    if response.clicked() {
-                                        selection_update = Some(cell_offset);
-                                    }
-                                }
-                            });
-                        });
+                    ui.add_space(6.0);
+                    ui.separator();
+                    ui.add_space(4.0);
+                    ui.scope(|ui| {
+                        let spacing = &mut ui.style_mut().spacing;
+                        spacing.item_spacing.x = 0.0;
+                        spacing.button_padding = vec2(2.0, 0.0);
+
+                        for (idx, byte) in row.bytes.iter().enumerate() {
+                            let cell_offset = row.offset + idx;
+                            let text =
+                                byte_text(&self.app, printable_ascii(*byte), *byte, cell_offset);
+                            let response =
+                                ui.add(SelectableLabel::new(self.is_selected(cell_offset), text));
+                            if response.clicked() {
+                                clicked = Some(cell_offset);
+                            }
+                        }
+                    });
+                });
 
-                        if row_selected {
-                            let clip = ui.clip_rect();
-                            let rect = row_response.response.rect;
-                            let needs_scroll =
-                                rect.top() < clip.top() || rect.bottom() > clip.bottom();
-                            if needs_scroll {
-                                match self.last_vertical_move {
-                                    Some(VerticalMove::Up) => {
-                                        self.log_scroll("up", row.offset, rect.top(), clip.top());
-                                        row_response.response.scroll_to_me(Some(Align::Min));
-                                    }
-                                    Some(VerticalMove::Down) => {
-                                        self.log_scroll(
-                                            "down",
-                                            row.offset,
-                                            rect.bottom(),
-                                            clip.bottom(),
-                                        );
-                                        row_response.response.scroll_to_me(Some(Align::Max));
-                                    }
-                                    None => {
-                                        if rect.top() < clip.top() {
-                                            self.log_scroll(
-                                                "up",
-                                                row.offset,
-                                                rect.top(),
-                                                clip.top(),
-                                            );
-                                            row_response.response.scroll_to_me(Some(Align::Min));
-                                        } else {
-                                            self.log_scroll(
-                                                "down",
-                                                row.offset,
-                                                rect.bottom(),
-                                                clip.bottom(),
-                                            );
-                                            row_response.response.scroll_to_me(Some(Align::Max));
-                                        }
-                                    }
+                if row_selected {
+                    let clip = ui.clip_rect();
+                    let rect = row_response.response.rect;
+                    let needs_scroll = rect.top() < clip.top() || rect.bottom() > clip.bottom();
+                    if needs_scroll {
+                        match self.last_vertical_move {
+                            Some(VerticalMove::Up) => {
+                                self.log_scroll("up", row.offset, rect.top(), clip.top());
+                                row_response.response.scroll_to_me(Some(Align::Min));
+                            }
+                            Some(VerticalMove::Down) => {
+                                self.log_scroll("down", row.offset, rect.bottom(), clip.bottom());
+                                row_response.response.scroll_to_me(Some(Align::Max));
+                            }
+                            None => {
+                                if rect.top() < clip.top() {
+                                    self.log_scroll("up", row.offset, rect.top(), clip.top());
+                                    row_response.response.scroll_to_me(Some(Align::Min));
+                                } else {
+                                    self.log_scroll(
+                                        "down",
+                                        row.offset,
+                                        rect.bottom(),
+                                        clip.bottom(),
+                                    );
+                                    row_response.response.scroll_to_me(Some(Align::Max));
                                 }
                             }
                         }
                     }
-                });
+                }
+            }
         });
+        self.pane_offsets[pane] = output.state.offset.y;
 
-        if let Some(offset) = selection_update {
-            self.select(offset);
-            self.last_vertical_move = None;
+        if clicked.is_some() {
+            self.app.focus_pane(pane);
+        }
+        clicked
+    }
+
+    /// Fit bytes per row to the panel width when `--width auto` is on.
+    fn fit_width(&mut self, ui: &egui::Ui) {
+        let mut available = ui.available_width();
+        if self.app.split() == Some(Split::Vertical) {
+            available = (available - ui.spacing().item_spacing.x) / 2.0;
+        }
+        let char_width = ui
+            .fonts(|fonts| fonts.glyph_width(&egui::TextStyle::Monospace.resolve(ui.style()), '0'));
+        if self
+            .app
+            .fit_bytes_per_row(|app, width| row_width(app, width, char_width) <= available)
+        {
+            self.reveal_selection = true;
+            ui.ctx().request_repaint();
         }
+    }
 
+    /// With scroll lock on, move the other pane by however far one pane scrolled.
+    fn sync_locked_panes(&mut self) {
+        if !self.app.is_scroll_locked() || self.pane_offsets.len() < 2 {
+            self.locked_offsets = self.pane_offsets.clone();
+            return;
+        }
+        if self.locked_offsets.len() == self.pane_offsets.len() {
+            let moved = (0..self.pane_offsets.len())
+                .find(|&pane| self.pane_offsets[pane] != self.locked_offsets[pane]);
+            if let Some(moved) = moved {
+                let delta = self.pane_offsets[moved] - self.locked_offsets[moved];
+                for pane in 0..self.pane_offsets.len() {
+                    if pane != moved {
+                        let offset = (self.pane_offsets[pane] + delta).max(0.0);
+                        self.pane_offsets[pane] = offset;
+                        self.scroll_requests[pane] = Some(offset);
+                    }
+                }
+            }
+        }
+        self.locked_offsets = self.pane_offsets.clone();
+    }
+
+    fn draw_status_panel(&mut self, ctx: &egui::Context) {
         TopBottomPanel::bottom("status_panel").show(ctx, |ui| {
-            ui.label(self.app.file_name());
+            ui.horizontal(|ui| {
+                ui.label(themed(
+                    &self.app,
+                    RichText::new(self.app.title()),
+                    |theme| theme.status,
+                ));
+                if self.editing {
+                    ui.label(RichText::new("EDIT").strong());
+                }
+            });
+            if let Some(message) = self.app.message() {
+                ui.label(message);
+            }
+            if self.app.is_changed_on_disk() && ui.button("Reload").clicked() {
+                self.reload();
+            }
+            ui.horizontal(|ui| {
+                let split = self.app.split();
+                if ui
+                    .selectable_label(split == Some(Split::Horizontal), "Split horizontally")
+                    .clicked()
+                {
+                    self.app.toggle_split(Split::Horizontal);
+                }
+                if ui
+                    .selectable_label(split == Some(Split::Vertical), "Split vertically")
+                    .clicked()
+                {
+                    self.app.toggle_split(Split::Vertical);
+                }
+                let mut grouping = self.app.grouping();
+                let mut base = self.app.base();
+                egui::ComboBox::from_label("Base")
+                    .selected_text(base.name())
+                    .show_ui(ui, |ui| {
+                        for &option in Base::value_variants() {
+                            ui.selectable_value(&mut base, option, option.name());
+                        }
+                    });
+                if base != self.app.base() {
+                    self.app.set_base(base);
+                }
+                egui::ComboBox::from_label("Group")
+                    .selected_text(grouping.size.to_string())
+                    .show_ui(ui, |ui| {
+                        for size in GROUP_SIZES {
+                            ui.selectable_value(&mut grouping.size, size, size.to_string());
+                        }
+                    });
+                ui.checkbox(&mut grouping.little_endian, "Little-endian");
+                if grouping != self.app.grouping() {
+                    self.app.set_grouping(grouping);
+                }
+                let mut locked = self.app.is_scroll_locked();
+                if ui
+                    .add_enabled(
+                        split.is_some(),
+                        egui::Checkbox::new(&mut locked, "Lock scroll"),
+                    )
+                    .changed()
+                {
+                    self.app.toggle_scroll_lock();
+                }
+            });
             ui.horizontal(|ui| {
                 ui.label(format!(
-                    "bytes: {} | rows: {} | width: {}",
+                    "bytes: {}{} | rows: {} | width: {}{}",
                     self.app.bytes_len(),
+                    self.app.source_state(),
                     self.app.total_rows(),
-                    self.app.bytes_per_row()
+                    self.app.bytes_per_row(),
+                    if self.app.is_auto_width() {
+                        " (auto)"
+                    } else {
+                        ""
+                    }
                 ));
-                if let Some(offset) = self.selected_offset {
-                    ui.label(format!("Selection: 0x{offset:08X}"));
+                if let Some(offset) = self.app.cursor() {
+                    ui.label(format!("Cursor: 0x{:08X}", self.app.display_offset(offset)));
                 } else {
-                    ui.label("Selection: none");
+                    ui.label("Cursor: none");
+                }
+            });
+        });
+    }
+
+    /// Fields of the applied template as a tree; clicking one selects its bytes.
+    fn draw_template_panel(&mut self, ctx: &egui::Context) {
+        let Some(root) = self.app.template_fields() else {
+            return;
+        };
+        let mut clicked = None;
+        SidePanel::right("template_panel")
+            .resizable(true)
+            .default_width(280.0)
+            .show(ctx, |ui| {
+                ui.heading(&root.name);
+                ui.label(format!(
+                    "0x{:X}, {} bytes",
+                    self.app.display_offset(root.range.start),
+                    root.range.len()
+                ));
+                ui.separator();
+                ScrollArea::vertical()
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        for child in &root.children {
+                            field_tree(ui, child, self.app.cursor(), &mut clicked);
+                        }
+                    });
+            });
+        if let Some(range) = clicked {
+            self.pending_jump = Some(range.start);
+            self.pending_nibble = None;
+            self.app.select_field(range);
+        }
+    }
+
+    /// Handle input for this tab, unless something else has the keyboard, and draw its
+    /// hex view and status bar.
+    fn show(&mut self, ctx: &egui::Context, tab_index: usize, accept_input: bool) {
+        // A reflow moves every byte, so bring the selection back into view afterwards.
+        let mut keyboard_moved = std::mem::take(&mut self.reveal_selection);
+        if accept_input {
+            keyboard_moved |= self.handle_keyboard_navigation(ctx);
+            self.handle_editing(ctx);
+        }
+        // Panels have to be added before the central panel they shrink.
+        if self.app.is_status_bar_visible() {
+            self.draw_status_panel(ctx);
+        }
+        self.draw_template_panel(ctx);
+
+        let mut selection_update: Option<usize> = None;
+        let total_rows = self.app.total_rows();
+        if self.app.pane_count() != self.pane_offsets.len() {
+            self.pane_offsets = vec![0.0; self.app.pane_count()];
+            self.scroll_requests = vec![None; self.app.pane_count()];
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            self.fit_width(ui);
+            ui.add_space(4.0);
+            if total_rows == 0 {
+                ScrollArea::vertical()
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        ui.label(self.app.empty_text());
+                    });
+                return;
+            }
+
+            match self.app.split() {
+                None => {
+                    selection_update = self.draw_pane(ui, tab_index, 0, keyboard_moved);
+                }
+                Some(Split::Horizontal) => {
+                    let size = vec2(
+                        ui.available_width(),
+                        (ui.available_height() - ui.spacing().item_spacing.y) / 2.0,
+                    );
+                    for pane in 0..2 {
+                        ui.allocate_ui(size, |ui| {
+                            if let Some(offset) =
+                                self.draw_pane(ui, tab_index, pane, keyboard_moved)
+                            {
+                                selection_update = Some(offset);
+                            }
+                        });
+                    }
+                }
+                Some(Split::Vertical) => {
+                    ui.columns(2, |columns| {
+                        for (pane, ui) in columns.iter_mut().enumerate() {
+                            if let Some(offset) =
+                                self.draw_pane(ui, tab_index, pane, keyboard_moved)
+                            {
+                                selection_update = Some(offset);
+                            }
+                        }
+                    });
+                }
+            }
+            self.sync_locked_panes();
+        });
+        if self.scroll_requests.iter().any(Option::is_some) || self.pending_jump.is_some() {
+            ctx.request_repaint();
+        }
+
+        if let Some(offset) = selection_update {
+            self.select(offset);
+            self.app.clear_selection();
+            self.last_vertical_move = None;
+            // The status panel was drawn before the click was seen.
+            ctx.request_repaint();
+        }
+    }
+}
+
+/// Ctrl+Shift+P list of every action and command, filtered as you type.
+struct Palette {
+    open: bool,
+    query: String,
+    /// Index of the highlighted entry among the matches.
+    selected: usize,
+    error: Option<String>,
+    /// Give the text field the keyboard on the next frame.
+    focus: bool,
+    entries: Vec<PaletteEntry>,
+}
+
+struct PaletteEntry {
+    name: &'static str,
+    description: &'static str,
+    keys: Option<String>,
+    target: PaletteTarget,
+}
+
+enum PaletteTarget {
+    Action(Action),
+    /// A command line command; ones taking arguments are typed out first.
+    Command(&'static str),
+}
+
+enum PaletteChoice {
+    Action(Action),
+    Command(String),
+}
+
+impl Palette {
+    fn new(keymap: &Keymap) -> Self {
+        let actions = ACTIONS
+            .iter()
+            .map(|&(action, name, description)| PaletteEntry {
+                name,
+                description,
+                keys: keymap.keys_for(action).map(ToString::to_string),
+                target: PaletteTarget::Action(action),
+            });
+        let commands = COMMANDS.iter().map(|&(name, usage)| PaletteEntry {
+            name,
+            description: usage,
+            keys: None,
+            target: PaletteTarget::Command(name),
+        });
+        Self {
+            open: false,
+            query: String::new(),
+            selected: 0,
+            error: None,
+            focus: false,
+            entries: commands.chain(actions).collect(),
+        }
+    }
+
+    fn toggle(&mut self) {
+        if self.open {
+            self.close();
+        } else {
+            self.open = true;
+            self.focus = true;
+        }
+    }
+
+    /// Open with `query` already typed, for commands that need arguments.
+    fn open_with(&mut self, query: &str) {
+        self.close();
+        self.query = query.to_string();
+        self.open = true;
+        self.focus = true;
+    }
+
+    fn close(&mut self) {
+        self.open = false;
+        self.query.clear();
+        self.selected = 0;
+        self.error = None;
+    }
+
+    /// Entries matching the query, best first. A query with arguments is a command line
+    /// and matches nothing.
+    fn matches(&self) -> Vec<usize> {
+        if self.query.trim_start().contains(' ') {
+            return Vec::new();
+        }
+        let query = self.query.trim();
+        let mut scored: Vec<(i32, usize)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, entry)| {
+                let score = fuzzy_score(query, entry.name).or_else(|| {
+                    fuzzy_score(query, entry.description).map(|score| score - 10_000)
+                })?;
+                Some((score, idx))
+            })
+            .collect();
+        if !query.is_empty() {
+            scored.sort_by_key(|(score, _)| -score);
+        }
+        scored.into_iter().map(|(_, idx)| idx).collect()
+    }
+
+    fn show(&mut self, ctx: &egui::Context) -> Option<PaletteChoice> {
+        let (up, down, enter, escape) = ctx.input_mut(|input| {
+            (
+                input.consume_key(Modifiers::NONE, Key::ArrowUp),
+                input.consume_key(Modifiers::NONE, Key::ArrowDown),
+                input.consume_key(Modifiers::NONE, Key::Enter),
+                input.consume_key(Modifiers::NONE, Key::Escape),
+            )
+        });
+        if escape {
+            self.close();
+            return None;
+        }
+
+        let matches = self.matches();
+        let is_command_line = self.query.trim_start().contains(' ');
+        if down {
+            self.selected = (self.selected + 1).min(matches.len().saturating_sub(1));
+        }
+        if up {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        let mut query = self.query.clone();
+        let mut clicked = None;
+        egui::Window::new("Command palette")
+            .title_bar(false)
+            .resizable(false)
+            .collapsible(false)
+            .anchor(Align2::CENTER_TOP, vec2(0.0, 40.0))
+            .default_width(520.0)
+            .show(ctx, |ui| {
+                let edit = ui.add(
+                    TextEdit::singleline(&mut query)
+                        .hint_text("Action or command, e.g. goto 0x100")
+                        .desired_width(f32::INFINITY),
+                );
+                if self.focus {
+                    edit.request_focus();
                 }
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+                if is_command_line {
+                    let name = self.query.split_whitespace().next().unwrap_or_default();
+                    let usage = COMMANDS
+                        .iter()
+                        .find(|(command, _)| *command == name)
+                        .map_or("", |(_, usage)| usage);
+                    ui.weak(format!("Enter runs the command. {usage}"));
+                    return;
+                }
+                ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                    for (idx, &entry) in matches.iter().enumerate() {
+                        let entry = &self.entries[entry];
+                        let selected = idx == self.selected;
+                        let response = ui
+                            .horizontal(|ui| {
+                                let response = ui.add(SelectableLabel::new(
+                                    selected,
+                                    RichText::new(entry.name).monospace(),
+                                ));
+                                ui.weak(entry.description);
+                                if let Some(keys) = &entry.keys {
+                                    ui.with_layout(
+                                        egui::Layout::right_to_left(Align::Center),
+                                        |ui| ui.label(RichText::new(keys).monospace()),
+                                    );
+                                }
+                                response
+                            })
+                            .inner;
+                        if selected && (up || down) {
+                            response.scroll_to_me(None);
+                        }
+                        if response.clicked() {
+                            clicked = Some(idx);
+                        }
+                    }
+                });
             });
+        self.focus = false;
+        let chosen = clicked.or(enter.then_some(self.selected));
+        let choice = if is_command_line {
+            enter.then(|| PaletteChoice::Command(self.query.trim().to_string()))
+        } else {
+            match chosen
+                .and_then(|idx| matches.get(idx))
+                .map(|&idx| &self.entries[idx].target)
+            {
+                Some(PaletteTarget::Action(action)) => Some(PaletteChoice::Action(*action)),
+                Some(PaletteTarget::Command(name)) if takes_arguments(name) => {
+                    query = format!("{name} ");
+                    self.focus = true;
+                    None
+                }
+                Some(PaletteTarget::Command(name)) => {
+                    Some(PaletteChoice::Command(name.to_string()))
+                }
+                None => None,
+            }
+        };
+        if query != self.query {
+            self.query = query;
+            self.selected = 0;
+            self.error = None;
+        }
+        choice
+    }
+}
+
+/// A menu entry that runs `action`, showing the keys bound to it.
+fn action_item(
+    ui: &mut egui::Ui,
+    keymap: &Keymap,
+    label: &str,
+    action: Action,
+    command: &mut Option<MenuCommand>,
+) {
+    let mut button = egui::Button::new(label);
+    if let Some(keys) = keymap.keys_for(action) {
+        button = button.shortcut_text(keys.to_string());
+    }
+    if ui.add(button).clicked() {
+        *command = Some(MenuCommand::Action(action));
+        ui.close_menu();
+    }
+}
+
+/// The key chord for an egui key press, to look up in the keymap.
+fn key_chord(key: Key, modifiers: Modifiers) -> Option<KeyChord> {
+    // Arrow symbols are single characters, so use their names instead.
+    let name = match key {
+        Key::ArrowDown | Key::ArrowLeft | Key::ArrowRight | Key::ArrowUp => key.name(),
+        _ => key.symbol_or_name(),
+    };
+    let mut text = String::new();
+    if modifiers.ctrl || modifiers.command {
+        text.push_str("ctrl+");
+    }
+    if modifiers.alt {
+        text.push_str("alt+");
+    }
+    if modifiers.shift {
+        text.push_str("shift+");
+    }
+    text.push_str(&name.to_ascii_lowercase());
+    text.parse().ok()
+}
+
+/// One template field and, collapsed below it, its children. Fields holding the cursor
+/// are highlighted and start open.
+fn field_tree(
+    ui: &mut egui::Ui,
+    field: &Field,
+    cursor: Option<usize>,
+    clicked: &mut Option<Range<usize>>,
+) {
+    let has_cursor = cursor.is_some_and(|cursor| field.range.contains(&cursor));
+    let label = if field.value.is_empty() {
+        field.name.clone()
+    } else {
+        format!("{}: {}", field.name, field.value)
+    };
+    if field.children.is_empty() {
+        if ui
+            .selectable_label(has_cursor, RichText::new(label).monospace())
+            .clicked()
+        {
+            *clicked = Some(field.range.clone());
+        }
+        return;
+    }
+    let response = egui::CollapsingHeader::new(RichText::new(label).monospace())
+        .id_source((field.range.start, &field.name))
+        .default_open(has_cursor)
+        .show(ui, |ui| {
+            for child in &field.children {
+                field_tree(ui, child, cursor, clicked);
+            }
         });
+    if response.header_response.clicked() {
+        *clicked = Some(field.range.clone());
     }
 }
 
+fn window_title(app: &App) -> String {
+    format!("{} - Rust Hex Viewer", app.title())
+}
+
+/// Monospace cell text for the byte at `index`, colored by byte class unless colors are
+/// off, with selected bytes and template fields shaded.
+fn byte_text(app: &App, text: String, byte: u8, index: usize) -> RichText {
+    let text = themed(app, RichText::new(text).monospace(), |theme| {
+        theme.bytes.color_of(byte)
+    });
+    let selected = app
+        .selection()
+        .is_some_and(|selection| selection.contains(&index));
+    let background = match app.theme() {
+        Some(theme) if selected => Some(theme.selection.into()),
+        None if selected => Some(Color32::from_gray(96)),
+        _ => app.field_color(index).map(Color32::from),
+    };
+    match background {
+        Some(color) => text.background_color(color),
+        None => text,
+    }
+}
+
+/// Color `text` with the theme's `color`, or leave it alone when colors are off.
+fn themed(app: &App, text: RichText, color: impl Fn(&Theme) -> Rgb) -> RichText {
+    match app.theme() {
+        Some(theme) => text.color(Color32::from(color(theme))),
+        None => text,
+    }
+}
+
+/// Base the egui visuals on the theme's background and use its cursor color for the
+/// selected byte.
+fn apply_visuals(ctx: &egui::Context, theme: &Theme) {
+    let mut visuals = if theme.dark {
+        egui::Visuals::dark()
+    } else {
+        egui::Visuals::light()
+    };
+    visuals.selection.bg_fill = theme.cursor.into();
+    ctx.set_visuals(visuals);
+}
+
 fn printable_ascii(byte: u8) -> String {
     if byte.is_ascii_graphic() || byte == b' ' {
         (byte as char).to_string()
@@ -285,7 +1527,36 @@ fn printable_ascii(byte: u8) -> String {
     }
 }
 
-fn draw_header(ui: &mut egui::Ui, bytes_per_row: usize) {
+/// Estimated width of a row of `width` bytes, following the layout in `draw_pane`: the
+/// offset column, separators and item spacing, then one padded cell per byte in the byte
+/// and ASCII columns plus the gaps between groups.
+fn row_width(app: &App, width: usize, char_width: f32) -> f32 {
+    const ROW_OVERHEAD: f32 = 100.0;
+    let base = app.base();
+    let grouping = base.grouping(app.grouping());
+    let groups = grouping.columns(width).len();
+    let mut gaps = groups.saturating_sub(1) as f32 * 4.0;
+    if (0..groups).any(|idx| grouping.gap_before(idx, width)) {
+        gaps += 4.0;
+    }
+    let byte_cell = base.cell_width() as f32 * char_width + 4.0;
+    let ascii_cell = char_width + 4.0;
+    ROW_OVERHEAD + 8.0 * char_width + width as f32 * (byte_cell + ascii_cell) + gaps
+}
+
+/// Space between byte groups, matching the gaps `format_line` leaves in the TUI.
+fn add_group_space(ui: &mut egui::Ui, grouping: Grouping, group_idx: usize, width: usize) {
+    if group_idx > 0 {
+        ui.add_space(4.0);
+    }
+    if grouping.gap_before(group_idx, width) {
+        ui.add_space(4.0);
+    }
+}
+
+fn draw_header(ui: &mut egui::Ui, bytes_per_row: usize, grouping: Grouping, base: Base) {
+    let grouping = base.grouping(grouping);
+    let cell_width = base.cell_width();
     ui.horizontal(|ui| {
         ui.monospace(format!("{:<8}", "Offset"));
         ui.add_space(4.0);
@@ -293,15 +1564,16 @@ fn draw_header(ui: &mut egui::Ui, bytes_per_row: usize) {
         ui.add_space(4.0);
         ui.scope(|ui| {
             let spacing = &mut ui.style_mut().spacing;
-            spacing.item_spacing.x = 4.0;
+            spacing.item_spacing.x = 0.0;
             spacing.button_padding = vec2(2.0, 0.0);
 
-            for idx in 0..bytes_per_row {
-                if idx == bytes_per_row / 2 {
-                    ui.add_space(4.0);
+            for (group_idx, group) in grouping.columns(bytes_per_row).iter().enumerate() {
+                add_group_space(ui, grouping, group_idx, bytes_per_row);
+                for idx in group {
+                    let text =
+                        RichText::new(format!("{:>cell_width$}", format!("{idx:02X}"))).monospace();
+                    ui.add(SelectableLabel::new(false, text));
                 }
-                let text = RichText::new(format!("{idx:02X}")).monospace();
-                ui.add(SelectableLabel::new(false, text));
             }
         });
         ui.add_space(6.0);
@@ -0,0 +1,140 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow::{Context, Result, bail};
+
+/// Read `length` bytes (or up to end of file) starting at `offset`.
+pub fn read_window(path: &Path, offset: usize, length: Option<usize>) -> Result<Vec<u8>> {
+    let context = || format!("Failed to read input file: {}", path.display());
+    let mut file = File::open(path).with_context(context)?;
+    let size = file.metadata().with_context(context)?.len();
+    if offset as u64 > size {
+        bail!(
+            "Offset 0x{offset:X} is past the end of {} (size 0x{size:X})",
+            path.display()
+        );
+    }
+    file.seek(SeekFrom::Start(offset as u64))
+        .with_context(context)?;
+    let mut bytes = Vec::new();
+    file.take(length.map_or(u64::MAX, |length| length as u64))
+        .read_to_end(&mut bytes)
+        .with_context(context)?;
+    Ok(bytes)
+}
+
+/// Size and modification time of a file, used to notice changes made by other programs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileStamp {
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+impl FileStamp {
+    pub fn read(path: &Path) -> std::io::Result<Self> {
+        let metadata = fs::metadata(path)?;
+        Ok(Self {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+}
+
+/// Whether `path` is a regular file whose permissions allow writing.
+pub fn is_writable(path: &Path) -> bool {
+    fs::metadata(path)
+        .is_ok_and(|metadata| metadata.is_file() && !metadata.permissions().readonly())
+}
+
+/// Overwrite the bytes at `offset` in `path` with `bytes`, keeping a `.bak` copy.
+///
+/// Used to save a buffer that may only cover a window of the file.
+pub fn write_window(path: &Path, offset: usize, bytes: &[u8]) -> Result<Option<PathBuf>> {
+    let mut data =
+        fs::read(path).with_context(|| format!("Failed to read file: {}", path.display()))?;
+    let end = offset + bytes.len();
+    if end > data.len() {
+        data.resize(end, 0);
+    }
+    data[offset..end].copy_from_slice(bytes);
+    write_with_backup(path, &data, true)
+}
+
+/// Path of the backup copy kept next to `path` (`<file>.bak`).
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".bak");
+    PathBuf::from(name)
+}
+
+/// Replace the contents of `path`, optionally keeping the previous version as `<file>.bak`.
+///
+/// The file is rewritten in place once the backup exists, so symlinks are written
+/// through and hardlinks, permissions and ownership are kept. Returns the backup path if
+/// one was written.
+pub fn write_with_backup(path: &Path, bytes: &[u8], backup: bool) -> Result<Option<PathBuf>> {
+    let backup_file = if backup {
+        let target = backup_path(path);
+        fs::copy(path, &target)
+            .with_context(|| format!("Failed to write backup file: {}", target.display()))?;
+        Some(target)
+    } else {
+        None
+    };
+
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .and_then(|mut file| {
+            file.write_all(bytes)?;
+            file.sync_all()
+        })
+        .with_context(|| format!("Failed to write file: {}", path.display()))?;
+
+    Ok(backup_file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writing_keeps_links_permissions_and_other_files() {
+        let dir = std::env::temp_dir().join(format!("rhv-write-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.bin");
+        let link = dir.join("hard.bin");
+        let tmp = dir.join("data.bin.tmp");
+        fs::write(&path, b"old").unwrap();
+        fs::hard_link(&path, &link).unwrap();
+        fs::write(&tmp, b"mine").unwrap();
+
+        let backup = write_with_backup(&path, b"new data", true).unwrap();
+        assert_eq!(backup, Some(backup_path(&path)));
+        assert_eq!(fs::read(backup_path(&path)).unwrap(), b"old");
+        assert_eq!(fs::read(&path).unwrap(), b"new data");
+        assert_eq!(fs::read(&link).unwrap(), b"new data");
+        assert_eq!(fs::read(&tmp).unwrap(), b"mine");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+            let symlink = dir.join("sym.bin");
+            std::os::unix::fs::symlink(&path, &symlink).unwrap();
+            write_with_backup(&symlink, b"via link", false).unwrap();
+            assert!(fs::symlink_metadata(&symlink).unwrap().is_symlink());
+            assert_eq!(fs::read(&path).unwrap(), b"via link");
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o640);
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,627 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{Result, anyhow, bail};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::args::KeymapPreset;
+
+/// Something a key can be bound to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    CommandLine,
+    Help,
+    Open,
+    Save,
+    ToggleEdit,
+    CursorLeft,
+    CursorRight,
+    CursorDown,
+    CursorUp,
+    ScrollDown,
+    ScrollUp,
+    PageDown,
+    PageUp,
+    GotoStart,
+    GotoEnd,
+    Reload,
+    SplitHorizontal,
+    SplitVertical,
+    NextPane,
+    ToggleScrollLock,
+    CycleBase,
+    CycleGroup,
+    ToggleLittleEndian,
+    ToggleStatusBar,
+    NextTab,
+    PrevTab,
+    /// Switch to the tab with this zero-based index.
+    SelectTab(usize),
+}
+
+/// Every action with its name in the config file and a short description.
+pub const ACTIONS: &[(Action, &str, &str)] = &[
+    (Action::Quit, "quit", "Quit"),
+    (
+        Action::CommandLine,
+        "command-line",
+        "Type a command such as goto or find",
+    ),
+    (Action::Help, "help", "Show the key bindings"),
+    (Action::Open, "open", "Open a file in a new tab (GUI only)"),
+    (Action::Save, "save", "Save the file"),
+    (
+        Action::ToggleEdit,
+        "toggle-edit",
+        "Let typed hex digits overwrite bytes (GUI only)",
+    ),
+    (
+        Action::CursorLeft,
+        "cursor-left",
+        "Move the cursor to the previous byte",
+    ),
+    (
+        Action::CursorRight,
+        "cursor-right",
+        "Move the cursor to the next byte",
+    ),
+    (
+        Action::CursorDown,
+        "cursor-down",
+        "Move the cursor down one row",
+    ),
+    (Action::CursorUp, "cursor-up", "Move the cursor up one row"),
+    (Action::ScrollDown, "scroll-down", "Scroll down one row"),
+    (Action::ScrollUp, "scroll-up", "Scroll up one row"),
+    (Action::PageDown, "page-down", "Scroll down one page"),
+    (Action::PageUp, "page-up", "Scroll up one page"),
+    (
+        Action::GotoStart,
+        "goto-start",
+        "Go to the start of the file",
+    ),
+    (Action::GotoEnd, "goto-end", "Go to the end of the file"),
+    (Action::Reload, "reload", "Reload the file from disk"),
+    (
+        Action::SplitHorizontal,
+        "split-horizontal",
+        "Split the view top and bottom",
+    ),
+    (
+        Action::SplitVertical,
+        "split-vertical",
+        "Split the view side by side",
+    ),
+    (Action::NextPane, "next-pane", "Focus the other pane"),
+    (
+        Action::ToggleScrollLock,
+        "toggle-scroll-lock",
+        "Scroll both panes together",
+    ),
+    (
+        Action::CycleBase,
+        "cycle-base",
+        "Cycle hex, binary, octal and decimal",
+    ),
+    (
+        Action::CycleGroup,
+        "cycle-group",
+        "Cycle the byte group size",
+    ),
+    (
+        Action::ToggleLittleEndian,
+        "toggle-little-endian",
+        "Show groups as little-endian words",
+    ),
+    (
+        Action::ToggleStatusBar,
+        "toggle-status-bar",
+        "Show or hide the status bar",
+    ),
+    (Action::NextTab, "next-tab", "Switch to the next tab"),
+    (Action::PrevTab, "prev-tab", "Switch to the previous tab"),
+    (Action::SelectTab(0), "tab-1", "Switch to tab 1"),
+    (Action::SelectTab(1), "tab-2", "Switch to tab 2"),
+    (Action::SelectTab(2), "tab-3", "Switch to tab 3"),
+    (Action::SelectTab(3), "tab-4", "Switch to tab 4"),
+    (Action::SelectTab(4), "tab-5", "Switch to tab 5"),
+    (Action::SelectTab(5), "tab-6", "Switch to tab 6"),
+    (Action::SelectTab(6), "tab-7", "Switch to tab 7"),
+    (Action::SelectTab(7), "tab-8", "Switch to tab 8"),
+    (Action::SelectTab(8), "tab-9", "Switch to tab 9"),
+];
+
+impl Action {
+    /// Whether this key leaves a "press again to confirm" prompt standing: the actions
+    /// that ask for one, and the command line, where `:w` confirms a save.
+    pub fn keeps_confirm(self) -> bool {
+        matches!(self, Action::Reload | Action::Save | Action::CommandLine)
+    }
+}
+
+impl FromStr for Action {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        ACTIONS
+            .iter()
+            .find(|(_, action_name, _)| *action_name == name)
+            .map(|(action, ..)| *action)
+            .ok_or_else(|| {
+                let names: Vec<&str> = ACTIONS.iter().map(|(_, name, _)| *name).collect();
+                anyhow!(
+                    "Unknown action `{name}` (expected one of: {})",
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// One key press with its modifiers, e.g. `ctrl+x` or `G`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        // Characters already carry their case, so Shift would only get in the way of
+        // matching `G` against shift+g.
+        let modifiers = match code {
+            KeyCode::Char(_) | KeyCode::BackTab => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        Self { code, modifiers }
+    }
+}
+
+impl From<KeyEvent> for KeyChord {
+    fn from(key: KeyEvent) -> Self {
+        let modifiers =
+            key.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        Self::new(key.code, modifiers)
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = anyhow::Error;
+
+    /// Parse `[ctrl+][alt+][shift+]KEY`, where KEY is a character or a name like
+    /// `space`, `pagedown` or `f1`.
+    fn from_str(text: &str) -> Result<Self> {
+        // A trailing `+` is the plus key itself, as in `ctrl++`.
+        let (prefix, key) = match text.strip_suffix("++") {
+            Some(prefix) => (prefix, "+"),
+            None if text == "+" => ("", "+"),
+            None => text.rsplit_once('+').unwrap_or(("", text)),
+        };
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in prefix.split('+').filter(|part| !part.is_empty()) {
+            modifiers |= match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => bail!("Unknown modifier `{modifier}` in `{text}`"),
+            };
+        }
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+                KeyCode::Char(c.to_ascii_uppercase())
+            }
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_ascii_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" | "ins" => KeyCode::Insert,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" | "pgup" => KeyCode::PageUp,
+                "pagedown" | "pgdn" => KeyCode::PageDown,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                name => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => bail!("Unknown key `{key}` in `{text}`"),
+                },
+            },
+        };
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "ctrl+"),
+            (KeyModifiers::ALT, "alt+"),
+            (KeyModifiers::SHIFT, "shift+"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                f.write_str(name)?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("space"),
+            // `ctrl+shift+p` reads better than `ctrl+P`.
+            KeyCode::Char(c) if c.is_ascii_uppercase() && !self.modifiers.is_empty() => {
+                write!(f, "shift+{}", c.to_ascii_lowercase())
+            }
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::BackTab => f.write_str("shift+tab"),
+            KeyCode::PageUp => f.write_str("pageup"),
+            KeyCode::PageDown => f.write_str("pagedown"),
+            KeyCode::F(n) => write!(f, "f{n}"),
+            code => write!(f, "{}", format!("{code:?}").to_ascii_lowercase()),
+        }
+    }
+}
+
+/// Keys pressed one after another, like `g t` or `ctrl+x ctrl+c`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeySequence(pub Vec<KeyChord>);
+
+impl FromStr for KeySequence {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let chords = text
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<Vec<_>>>()?;
+        if chords.is_empty() {
+            bail!("Empty key binding");
+        }
+        Ok(Self(chords))
+    }
+}
+
+impl fmt::Display for KeySequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, chord) in self.0.iter().enumerate() {
+            if idx > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{chord}")?;
+        }
+        Ok(())
+    }
+}
+
+/// What a run of keys means under a keymap.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Lookup {
+    /// The action bound to exactly these keys.
+    pub action: Option<Action>,
+    /// Whether a longer binding starts with these keys.
+    pub is_prefix: bool,
+}
+
+/// Key sequences bound to actions.
+#[derive(Clone, Debug)]
+pub struct Keymap {
+    bindings: Vec<(KeySequence, Action)>,
+}
+
+impl Keymap {
+    pub fn preset(preset: KeymapPreset) -> Self {
+        Self::with_keys(match preset {
+            KeymapPreset::Default => DEFAULT_KEYS,
+            KeymapPreset::Vim => VIM_KEYS,
+            KeymapPreset::Emacs => EMACS_KEYS,
+        })
+    }
+
+    /// Keys for the GUI, where letters and digits edit the selected byte.
+    pub fn gui() -> Self {
+        Self::with_keys(GUI_KEYS)
+    }
+
+    fn with_keys(bindings: &[(&str, Action)]) -> Self {
+        let mut keymap = Self {
+            bindings: Vec::new(),
+        };
+        for (keys, action) in bindings {
+            let keys = keys.parse().expect("preset keys are valid");
+            keymap.bind(keys, Some(*action));
+        }
+        for tab in 0..9 {
+            let keys = format!("alt+{}", tab + 1).parse().expect("valid key");
+            keymap.bind(keys, Some(Action::SelectTab(tab)));
+        }
+        keymap
+    }
+
+    /// Bind `keys` to `action`, replacing an existing binding; `None` unbinds them.
+    pub fn bind(&mut self, keys: KeySequence, action: Option<Action>) {
+        self.bindings.retain(|(bound, _)| *bound != keys);
+        if let Some(action) = action {
+            self.bindings.push((keys, action));
+        }
+    }
+
+    /// The first keys bound to `action`, if any.
+    pub fn keys_for(&self, action: Action) -> Option<&KeySequence> {
+        self.bindings
+            .iter()
+            .find(|(_, bound)| *bound == action)
+            .map(|(keys, _)| keys)
+    }
+
+    /// Every bound action as its keys, name and description, in the order of `ACTIONS`.
+    pub fn help(&self) -> Vec<(String, &'static str, &'static str)> {
+        ACTIONS
+            .iter()
+            .filter_map(|&(action, name, description)| {
+                let keys: Vec<String> = self
+                    .bindings
+                    .iter()
+                    .filter(|(_, bound)| *bound == action)
+                    .map(|(keys, _)| keys.to_string())
+                    .collect();
+                (!keys.is_empty()).then(|| (keys.join(", "), name, description))
+            })
+            .collect()
+    }
+
+    pub fn lookup(&self, keys: &[KeyChord]) -> Lookup {
+        let mut lookup = Lookup {
+            action: None,
+            is_prefix: false,
+        };
+        for (bound, action) in &self.bindings {
+            if bound.0 == keys {
+                lookup.action = Some(*action);
+            } else if bound.0.starts_with(keys) {
+                lookup.is_prefix = true;
+            }
+        }
+        lookup
+    }
+
+    /// Look up `chord` typed after the keys in `pending`, starting over from `chord` when
+    /// the longer run is not bound. `pending` keeps the keys while a longer binding may
+    /// still follow; returns the lookup and how many keys it covered.
+    pub fn press(&self, pending: &mut Vec<KeyChord>, chord: KeyChord) -> (Lookup, usize) {
+        let mut sequence = std::mem::take(pending);
+        sequence.push(chord);
+        let mut lookup = self.lookup(&sequence);
+        if lookup.action.is_none() && !lookup.is_prefix && sequence.len() > 1 {
+            // Not a binding after all; start over from the key just pressed.
+            sequence = vec![chord];
+            lookup = self.lookup(&sequence);
+        }
+        let len = sequence.len();
+        if lookup.is_prefix {
+            *pending = sequence;
+        }
+        (lookup, len)
+    }
+}
+
+const DEFAULT_KEYS: &[(&str, Action)] = &[
+    ("q", Action::Quit),
+    ("esc", Action::Quit),
+    (":", Action::CommandLine),
+    ("?", Action::Help),
+    ("f1", Action::Help),
+    ("ctrl+s", Action::Save),
+    ("h", Action::CursorLeft),
+    ("left", Action::CursorLeft),
+    ("l", Action::CursorRight),
+    ("right", Action::CursorRight),
+    ("down", Action::CursorDown),
+    ("up", Action::CursorUp),
+    ("j", Action::ScrollDown),
+    ("k", Action::ScrollUp),
+    ("space", Action::PageDown),
+    ("pagedown", Action::PageDown),
+    ("pageup", Action::PageUp),
+    ("g", Action::GotoStart),
+    ("home", Action::GotoStart),
+    ("G", Action::GotoEnd),
+    ("end", Action::GotoEnd),
+    ("g t", Action::NextTab),
+    ("g T", Action::PrevTab),
+    ("r", Action::Reload),
+    ("s", Action::SplitHorizontal),
+    ("v", Action::SplitVertical),
+    ("w", Action::NextPane),
+    ("tab", Action::NextPane),
+    ("L", Action::ToggleScrollLock),
+    ("b", Action::CycleBase),
+    ("=", Action::CycleGroup),
+    ("e", Action::ToggleLittleEndian),
+];
+
+const VIM_KEYS: &[(&str, Action)] = &[
+    ("q", Action::Quit),
+    ("Z Q", Action::Quit),
+    (":", Action::CommandLine),
+    ("?", Action::Help),
+    ("f1", Action::Help),
+    ("h", Action::CursorLeft),
+    ("left", Action::CursorLeft),
+    ("l", Action::CursorRight),
+    ("right", Action::CursorRight),
+    ("j", Action::CursorDown),
+    ("down", Action::CursorDown),
+    ("k", Action::CursorUp),
+    ("up", Action::CursorUp),
+    ("ctrl+e", Action::ScrollDown),
+    ("ctrl+y", Action::ScrollUp),
+    ("ctrl+f", Action::PageDown),
+    ("space", Action::PageDown),
+    ("pagedown", Action::PageDown),
+    ("ctrl+b", Action::PageUp),
+    ("pageup", Action::PageUp),
+    ("g g", Action::GotoStart),
+    ("home", Action::GotoStart),
+    ("G", Action::GotoEnd),
+    ("end", Action::GotoEnd),
+    ("g t", Action::NextTab),
+    ("g T", Action::PrevTab),
+    ("r", Action::Reload),
+    ("ctrl+w s", Action::SplitHorizontal),
+    ("ctrl+w v", Action::SplitVertical),
+    ("ctrl+w w", Action::NextPane),
+    ("L", Action::ToggleScrollLock),
+    ("b", Action::CycleBase),
+    ("=", Action::CycleGroup),
+    ("e", Action::ToggleLittleEndian),
+];
+
+/// Standard Emacs keys where there is one; viewer-specific actions live under the
+/// user-reserved `ctrl+c` prefix.
+const EMACS_KEYS: &[(&str, Action)] = &[
+    ("ctrl+x ctrl+c", Action::Quit),
+    ("alt+x", Action::CommandLine),
+    ("?", Action::Help),
+    ("f1", Action::Help),
+    ("ctrl+x ctrl+s", Action::Save),
+    ("ctrl+b", Action::CursorLeft),
+    ("left", Action::CursorLeft),
+    ("ctrl+f", Action::CursorRight),
+    ("right", Action::CursorRight),
+    ("ctrl+n", Action::CursorDown),
+    ("down", Action::CursorDown),
+    ("ctrl+p", Action::CursorUp),
+    ("up", Action::CursorUp),
+    ("ctrl+v", Action::PageDown),
+    ("pagedown", Action::PageDown),
+    ("alt+v", Action::PageUp),
+    ("pageup", Action::PageUp),
+    ("alt+<", Action::GotoStart),
+    ("home", Action::GotoStart),
+    ("alt+>", Action::GotoEnd),
+    ("end", Action::GotoEnd),
+    ("ctrl+x right", Action::NextTab),
+    ("ctrl+x left", Action::PrevTab),
+    ("ctrl+x ctrl+r", Action::Reload),
+    ("ctrl+x 2", Action::SplitHorizontal),
+    ("ctrl+x 3", Action::SplitVertical),
+    ("ctrl+x o", Action::NextPane),
+    ("ctrl+c l", Action::ToggleScrollLock),
+    ("ctrl+c b", Action::CycleBase),
+    ("ctrl+c g", Action::CycleGroup),
+    ("ctrl+c e", Action::ToggleLittleEndian),
+];
+
+const GUI_KEYS: &[(&str, Action)] = &[
+    ("ctrl+q", Action::Quit),
+    ("ctrl+shift+p", Action::CommandLine),
+    ("f1", Action::Help),
+    ("ctrl+o", Action::Open),
+    ("ctrl+s", Action::Save),
+    ("insert", Action::ToggleEdit),
+    ("pagedown", Action::PageDown),
+    ("pageup", Action::PageUp),
+    ("home", Action::GotoStart),
+    ("end", Action::GotoEnd),
+    ("r", Action::Reload),
+    ("ctrl+tab", Action::NextTab),
+    ("ctrl+shift+tab", Action::PrevTab),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chord(text: &str) -> KeyChord {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn chords_parse_modifiers_and_names() {
+        assert_eq!(
+            chord("ctrl+x"),
+            KeyChord::new(KeyCode::Char('x'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(chord("shift+g"), chord("G"));
+        assert_eq!(chord("shift+tab"), chord("backtab"));
+        assert_eq!(chord("ctrl++").code, KeyCode::Char('+'));
+        assert_eq!(chord("+").code, KeyCode::Char('+'));
+        assert_eq!(chord("PgDn").code, KeyCode::PageDown);
+        assert_eq!(chord("f12").code, KeyCode::F(12));
+        assert!("f13".parse::<KeyChord>().is_err());
+        assert!("super+x".parse::<KeyChord>().is_err());
+        assert!("".parse::<KeySequence>().is_err());
+    }
+
+    #[test]
+    fn chords_display_as_they_parse() {
+        for text in [
+            "ctrl+shift+p",
+            "alt+1",
+            "space",
+            "shift+tab",
+            "pagedown",
+            "f1",
+            "G",
+        ] {
+            assert_eq!(chord(text).to_string(), text);
+        }
+        let sequence: KeySequence = "g  t".parse().unwrap();
+        assert_eq!(sequence.to_string(), "g t");
+    }
+
+    #[test]
+    fn lookup_reports_actions_and_prefixes() {
+        let keymap = Keymap::preset(KeymapPreset::Vim);
+        let g = chord("g");
+        let t = chord("t");
+        assert!(keymap.lookup(&[g]).is_prefix);
+        assert_eq!(keymap.lookup(&[g, t]).action, Some(Action::NextTab));
+        assert_eq!(
+            keymap.lookup(&[chord("z")]),
+            Lookup {
+                action: None,
+                is_prefix: false
+            }
+        );
+    }
+
+    #[test]
+    fn press_collects_sequences_and_starts_over() {
+        let keymap = Keymap::preset(KeymapPreset::Vim);
+        let mut pending = Vec::new();
+        let (lookup, _) = keymap.press(&mut pending, chord("g"));
+        assert!(lookup.is_prefix);
+        assert_eq!(pending, [chord("g")]);
+        let (lookup, len) = keymap.press(&mut pending, chord("t"));
+        assert_eq!((lookup.action, len), (Some(Action::NextTab), 2));
+        assert!(pending.is_empty());
+        keymap.press(&mut pending, chord("g"));
+        let (lookup, len) = keymap.press(&mut pending, chord("j"));
+        assert_eq!((lookup.action, len), (Some(Action::CursorDown), 1));
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn bind_replaces_and_unbinds() {
+        let mut keymap = Keymap::gui();
+        let keys: KeySequence = "ctrl+q".parse().unwrap();
+        keymap.bind(keys.clone(), Some(Action::Help));
+        assert_eq!(keymap.lookup(&keys.0).action, Some(Action::Help));
+        keymap.bind(keys.clone(), None);
+        assert_eq!(keymap.lookup(&keys.0).action, None);
+        assert!(keymap.keys_for(Action::Quit).is_none());
+    }
+
+    #[test]
+    fn every_action_name_parses() {
+        for &(action, name, _) in ACTIONS {
+            assert_eq!(name.parse::<Action>().unwrap(), action);
+        }
+        assert!("fly".parse::<Action>().is_err());
+    }
+}
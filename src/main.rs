@@ -1,27 +1,159 @@
 mod app;
 mod args;
+mod cmdline;
+mod commands;
+mod config;
+mod diff;
+mod dump;
 mod gui;
+mod io;
+mod keymap;
+mod parse;
+mod patchfile;
+mod recent;
+mod source;
+mod tabs;
+mod template;
+mod theme;
 mod tui;
 
-use std::fs;
+use std::{
+    io::{IsTerminal, stdin},
+    path::Path,
+    process::ExitCode,
+};
 
-use anyhow::{Context, Result};
-use clap::Parser;
+use anyhow::{Context, Result, bail};
+use clap::{CommandFactory, FromArgMatches};
 
-use crate::{app::App, args::Args};
+use crate::{
+    app::App,
+    args::{Args, Command, ViewArgs, Width},
+    config::Config,
+    source::Follower,
+    tabs::Tabs,
+    template::Template,
+    theme::Theme,
+};
 
-fn main() -> Result<()> {
-    let args = Args::parse();
-    let bytes = fs::read(&args.path)
-        .with_context(|| format!("Failed to read input file: {}", args.path.display()))?;
-    let file_name = args.path.display().to_string();
+fn main() -> Result<ExitCode> {
+    let matches = Args::command().get_matches();
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
 
-    let app = App::new(file_name, bytes, args.clamped_bytes_per_row());
+    match args.into_command() {
+        Command::View(view_args) => {
+            if view_args.paths.is_empty() && stdin().is_terminal() && !view_args.gui {
+                Args::command().print_help()?;
+                return Ok(ExitCode::from(2));
+            }
+            view(view_args)?;
+        }
+        Command::Diff(diff_args) => return Ok(commands::diff::run(&diff_args)),
+        Command::Patch(patch_args) => {
+            let patch_matches = matches
+                .subcommand_matches("patch")
+                .context("Missing patch arguments")?;
+            commands::patch::run(&patch_args, patch_matches)?;
+        }
+        Command::Apply(apply_args) => commands::apply::run(&apply_args)?,
+        Command::Dump(dump_args) => commands::dump::run_dump(&dump_args)?,
+        Command::Undump(undump_args) => commands::dump::run_undump(&undump_args)?,
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn view(mut args: ViewArgs) -> Result<()> {
+    let config = Config::load(args.config.as_deref())?;
+    args.apply_config(&config);
+    let sources = args.sources();
+    if sources.iter().filter(|source| source.is_none()).count() > 1 {
+        bail!("stdin (`-`) can only be opened once");
+    }
+    let theme = args.theme(&config)?;
+    let keymap = if args.gui {
+        config.gui_keymap()?
+    } else {
+        config.keymap(args.keymap.unwrap_or_default())?
+    };
+    let apps = if args.gui && args.paths.is_empty() && stdin().is_terminal() {
+        // Started without a file; one can be opened from the menu.
+        let mut app = App::new(
+            "untitled".to_string(),
+            Vec::new(),
+            args.clamped_bytes_per_row(),
+        );
+        app.set_read_only(true);
+        app.set_theme(theme.clone());
+        vec![app]
+    } else {
+        sources
+            .into_iter()
+            .map(|source| open_app(&args, source, theme.clone()))
+            .collect::<Result<Vec<_>>>()?
+    };
+    let mut tabs = Tabs::new(apps);
 
     if args.gui {
-        gui::run(app, args.debug)
+        let debug = args.debug;
+        // Files opened from the GUI get the same view options as those on the command line.
+        let open = move |path: &Path| open_app(&args, Some(path), theme.clone());
+        gui::run(tabs, keymap, debug, Box::new(open))
     } else {
-        let mut app = app;
-        tui::run(&mut app)
+        tui::run(&mut tabs, &keymap, !args.no_mouse)
+    }
+}
+
+/// Load one file (or stdin when `path` is `None`) into a buffer for its own tab.
+fn open_app(args: &ViewArgs, path: Option<&Path>, theme: Option<Theme>) -> Result<App> {
+    let mut app = match path {
+        Some(path) => {
+            let bytes = io::read_window(path, args.offset, args.length)?;
+            let loaded = bytes.len();
+            let mut app = App::new(
+                path.display().to_string(),
+                bytes,
+                args.clamped_bytes_per_row(),
+            );
+            if args.follow {
+                let position = (args.offset + loaded) as u64;
+                let follower = Follower::open(path, position)
+                    .with_context(|| format!("Failed to follow file: {}", path.display()))?;
+                app.set_follow(follower);
+            }
+            app.set_path(path.to_path_buf());
+            app.set_read_only(args.readonly || !io::is_writable(path));
+            app
+        }
+        None => {
+            // Keyboard input comes from /dev/tty, so stdin is free to stream the data.
+            let mut app = App::new(
+                "<stdin>".to_string(),
+                Vec::new(),
+                args.clamped_bytes_per_row(),
+            );
+            let limit = args.length.map(|length| length as u64);
+            app.attach_stream(source::spawn_reader(stdin(), args.offset as u64, limit));
+            app.set_read_only(true);
+            app
+        }
+    };
+    app.set_window(args.offset, args.length, args.relative_offsets);
+    app.set_grouping(args.grouping());
+    app.set_base(args.base());
+    app.set_auto_width(args.width() == Width::Auto);
+    app.set_theme(theme);
+    if let Some(template_path) = &args.template {
+        if app.is_streaming() {
+            bail!("--template needs a file, not stdin");
+        }
+        let template = Template::load(template_path)?;
+        let index = match args.template_offset {
+            Some(offset) => app
+                .index_of_offset(offset)
+                .with_context(|| format!("Template offset 0x{offset:X} is outside the data"))?,
+            None => 0,
+        };
+        app.set_template(template, index)?;
     }
+    Ok(app)
 }
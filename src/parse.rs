@@ -0,0 +1,91 @@
+/// Parse an offset or count written in decimal or with a `0x` prefix.
+pub fn parse_number(text: &str) -> Result<usize, String> {
+    let text = text.trim().replace('_', "");
+    let parsed = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    parsed.map_err(|_| format!("invalid number: {text:?}"))
+}
+
+/// Parse a single byte value, following the same rule as `parse_number`: decimal such as
+/// `255`, or hex with a `0x` prefix such as `0xFF`.
+pub fn parse_byte(text: &str) -> Result<u8, String> {
+    let text = text.trim();
+    let value = parse_number(text).map_err(|_| format!("invalid byte: {text:?}"))?;
+    u8::try_from(value).map_err(|_| format!("byte out of range (0-255): {text:?}"))
+}
+
+/// Parse a hex byte string such as `DE AD BE EF`, `deadbeef` or `0xDE,0xAD`.
+pub fn parse_hex_bytes(text: &str) -> Result<Vec<u8>, String> {
+    let mut digits = String::with_capacity(text.len());
+    for token in text.split(|c: char| c.is_whitespace() || c == ',') {
+        let token = token
+            .strip_prefix("0x")
+            .or_else(|| token.strip_prefix("0X"))
+            .unwrap_or(token);
+        match token.len() {
+            1 => digits.push('0'),
+            len if len % 2 != 0 => return Err(format!("odd number of hex digits in {token:?}")),
+            _ => {}
+        }
+        digits.push_str(token);
+    }
+    if digits.is_empty() {
+        return Err("no bytes given".to_string());
+    }
+    if !digits.is_ascii() {
+        return Err(format!("invalid hex bytes: {text:?}"));
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|idx| {
+            u8::from_str_radix(&digits[idx..idx + 2], 16)
+                .map_err(|_| format!("invalid hex byte: {:?}", &digits[idx..idx + 2]))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_are_decimal_unless_prefixed() {
+        assert_eq!(parse_number("10"), Ok(10));
+        assert_eq!(parse_number("0x10"), Ok(16));
+        assert_eq!(parse_number("0X1_000"), Ok(0x1000));
+        assert_eq!(parse_number(" 1_024 "), Ok(1024));
+        assert!(parse_number("ff").is_err());
+        assert!(parse_number("").is_err());
+    }
+
+    #[test]
+    fn bytes_follow_the_number_rule() {
+        assert_eq!(parse_byte("10"), Ok(10));
+        assert_eq!(parse_byte("99"), Ok(99));
+        assert_eq!(parse_byte("255"), Ok(255));
+        assert_eq!(parse_byte("0xFF"), Ok(0xFF));
+        assert_eq!(parse_byte("0x0a"), Ok(10));
+        assert!(parse_byte("FF").is_err());
+        assert!(parse_byte("256").is_err());
+        assert!(parse_byte("0x100").is_err());
+    }
+
+    #[test]
+    fn hex_bytes_accept_common_spellings() {
+        let expected = Ok(vec![0xDE, 0xAD, 0xBE, 0xEF]);
+        assert_eq!(parse_hex_bytes("DE AD BE EF"), expected);
+        assert_eq!(parse_hex_bytes("deadbeef"), expected);
+        assert_eq!(parse_hex_bytes("0xDE,0xAD, 0xBE,0xEF"), expected);
+        assert_eq!(parse_hex_bytes("1 2"), Ok(vec![1, 2]));
+    }
+
+    #[test]
+    fn hex_bytes_reject_bad_input() {
+        assert!(parse_hex_bytes("").is_err());
+        assert!(parse_hex_bytes("ABC").is_err());
+        assert!(parse_hex_bytes("GG").is_err());
+        assert!(parse_hex_bytes("é1").is_err());
+    }
+}
@@ -0,0 +1,650 @@
+use std::{fmt::Write as FmtWrite, path::Path};
+
+use anyhow::{Context, Result, ensure};
+
+use crate::{
+    args::PatchFormat,
+    diff::{self, DiffKind},
+    parse::{parse_hex_bytes, parse_number},
+};
+
+const IPS_MAGIC: &[u8] = b"PATCH";
+const IPS_EOF: &[u8] = b"EOF";
+const IPS_EOF_OFFSET: usize = 0x454F46;
+const IPS_MAX_OFFSET: usize = 0xFF_FFFF;
+const IPS_MAX_RECORD: usize = 0xFFFF;
+const BPS_MAGIC: &[u8] = b"BPS1";
+const TEXT_HEADER: &str = "# rust-hex-viewer patch";
+
+/// A decoded patch file.
+pub enum Patch {
+    /// IPS records; IPS carries no original bytes, so these cannot be verified.
+    Ips {
+        records: Vec<(usize, Vec<u8>)>,
+        truncate: Option<usize>,
+    },
+    /// BPS action stream, verified through its source/target CRC32s.
+    Bps(Vec<u8>),
+    /// Text `offset: old -> new` lines; `old` is checked before writing.
+    Text {
+        records: Vec<TextRecord>,
+        truncate: Option<usize>,
+    },
+}
+
+/// One line of the text format.
+pub struct TextRecord {
+    pub offset: usize,
+    pub old: Vec<u8>,
+    pub new: Vec<u8>,
+}
+
+impl Patch {
+    pub fn format(&self) -> PatchFormat {
+        match self {
+            Patch::Ips { .. } => PatchFormat::Ips,
+            Patch::Bps(_) => PatchFormat::Bps,
+            Patch::Text { .. } => PatchFormat::Text,
+        }
+    }
+
+    /// Produce the patched buffer, failing if `source` is not what the patch was made from.
+    pub fn apply(&self, source: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Patch::Ips { records, truncate } => apply_ips(records, *truncate, source),
+            Patch::Bps(data) => apply_bps(data, source),
+            Patch::Text { records, truncate } => apply_text(records, *truncate, source),
+        }
+    }
+}
+
+/// Choose a patch format from a file extension, defaulting to text.
+pub fn format_for_path(path: &Path) -> PatchFormat {
+    match path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("ips") => PatchFormat::Ips,
+        Some("bps") => PatchFormat::Bps,
+        _ => PatchFormat::Text,
+    }
+}
+
+/// Encode the changes turning `original` into `patched`.
+pub fn encode(format: PatchFormat, original: &[u8], patched: &[u8]) -> Result<Vec<u8>> {
+    match format {
+        PatchFormat::Ips => encode_ips(original, patched),
+        PatchFormat::Bps => Ok(encode_bps(original, patched)),
+        PatchFormat::Text => Ok(encode_text(original, patched).into_bytes()),
+    }
+}
+
+/// Decode a patch, detecting the format from its header.
+pub fn decode(data: &[u8]) -> Result<Patch> {
+    if data.starts_with(IPS_MAGIC) {
+        decode_ips(data)
+    } else if data.starts_with(BPS_MAGIC) {
+        ensure!(data.len() >= BPS_MAGIC.len() + 12, "BPS patch is truncated");
+        let (body, footer) = data.split_at(data.len() - 4);
+        ensure!(
+            crc32(body) == read_u32_le(footer),
+            "BPS patch checksum mismatch"
+        );
+        Ok(Patch::Bps(data.to_vec()))
+    } else {
+        let text = std::str::from_utf8(data).context("Unrecognized patch format")?;
+        decode_text(text)
+    }
+}
+
+/// Ranges of `patched` that differ from `original` (growth at the end counts as a change).
+fn changed_ranges(original: &[u8], patched: &[u8]) -> Vec<std::ops::Range<usize>> {
+    diff::compare(original, patched)
+        .into_iter()
+        .filter(|op| matches!(op.kind, DiffKind::Replace | DiffKind::Insert))
+        .map(|op| op.right)
+        .collect()
+}
+
+fn encode_text(original: &[u8], patched: &[u8]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "{TEXT_HEADER}");
+    for range in changed_ranges(original, patched) {
+        let old_end = range.end.min(original.len());
+        let old = original.get(range.start..old_end).unwrap_or_default();
+        let _ = writeln!(
+            out,
+            "0x{:08X}: {} -> {}",
+            range.start,
+            hex_or_dash(old),
+            hex_or_dash(&patched[range.clone()])
+        );
+    }
+    if patched.len() < original.len() {
+        let _ = writeln!(out, "truncate: 0x{:08X}", patched.len());
+    }
+    out
+}
+
+fn hex_or_dash(bytes: &[u8]) -> String {
+    if bytes.is_empty() {
+        return "-".to_string();
+    }
+    let mut out = String::with_capacity(bytes.len() * 3);
+    for (idx, byte) in bytes.iter().enumerate() {
+        if idx > 0 {
+            out.push(' ');
+        }
+        let _ = write!(out, "{byte:02X}");
+    }
+    out
+}
+
+fn decode_text(text: &str) -> Result<Patch> {
+    let mut records = Vec::new();
+    let mut truncate = None;
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(size) = line.strip_prefix("truncate:") {
+            let size = parse_number(size).map_err(anyhow::Error::msg);
+            truncate = Some(size.with_context(|| format!("Invalid patch line {}", idx + 1))?);
+            continue;
+        }
+        let parse_line = || -> Result<TextRecord> {
+            let (offset, change) = line
+                .split_once(':')
+                .context("expected `offset: old -> new`")?;
+            let (old, new) = change.split_once("->").context("expected `old -> new`")?;
+            let parse_side = |side: &str| -> Result<Vec<u8>> {
+                let side = side.trim();
+                if side == "-" {
+                    Ok(Vec::new())
+                } else {
+                    parse_hex_bytes(side).map_err(anyhow::Error::msg)
+                }
+            };
+            Ok(TextRecord {
+                offset: parse_number(offset).map_err(anyhow::Error::msg)?,
+                old: parse_side(old)?,
+                new: parse_side(new)?,
+            })
+        };
+        records.push(parse_line().with_context(|| format!("Invalid patch line {}", idx + 1))?);
+    }
+    Ok(Patch::Text { records, truncate })
+}
+
+fn apply_text(records: &[TextRecord], truncate: Option<usize>, source: &[u8]) -> Result<Vec<u8>> {
+    let mut out = source.to_vec();
+    for record in records {
+        ensure!(
+            record.offset <= source.len(),
+            "Record at 0x{:08X} starts past the end of the file",
+            record.offset
+        );
+        let old_end = record
+            .offset
+            .checked_add(record.old.len())
+            .context("Patch record is out of range")?;
+        let actual = source
+            .get(record.offset..old_end.min(source.len()))
+            .unwrap_or_default();
+        ensure!(
+            actual == record.old.as_slice(),
+            "Original bytes at 0x{:08X} do not match: expected {}, found {}",
+            record.offset,
+            hex_or_dash(&record.old),
+            hex_or_dash(actual)
+        );
+        // Records overwrite bytes one for one. Only a record reaching the end of the file
+        // may add bytes, and shortening the file is left to `truncate:`.
+        ensure!(
+            record.old.len() <= record.new.len(),
+            "Record at 0x{:08X} replaces {} bytes with {}; shorten the file with `truncate:`",
+            record.offset,
+            record.old.len(),
+            record.new.len()
+        );
+        ensure!(
+            record.old.len() == record.new.len() || old_end == source.len(),
+            "Record at 0x{:08X} grows the data but does not end at end of file",
+            record.offset
+        );
+        let end = record
+            .offset
+            .checked_add(record.new.len())
+            .context("Patch record is out of range")?;
+        if end > out.len() {
+            out.resize(end, 0);
+        }
+        out[record.offset..end].copy_from_slice(&record.new);
+    }
+    if let Some(size) = truncate {
+        out.truncate(size);
+    }
+    Ok(out)
+}
+
+fn encode_ips(original: &[u8], patched: &[u8]) -> Result<Vec<u8>> {
+    let mut out = IPS_MAGIC.to_vec();
+    for range in changed_ranges(original, patched) {
+        // A record starting at 0x454F46 would read as the "EOF" marker; start one byte earlier.
+        let mut start = range.start;
+        if start == IPS_EOF_OFFSET {
+            start -= 1;
+        }
+        while start < range.end {
+            let end = (start + IPS_MAX_RECORD).min(range.end);
+            ensure!(
+                start <= IPS_MAX_OFFSET,
+                "IPS cannot address offset 0x{start:X} (limit is 16 MiB)"
+            );
+            let chunk = &patched[start..end];
+            out.extend_from_slice(&(start as u32).to_be_bytes()[1..]);
+            if chunk.len() > 3 && chunk.iter().all(|&byte| byte == chunk[0]) {
+                out.extend_from_slice(&[0, 0]);
+                out.extend_from_slice(&(chunk.len() as u16).to_be_bytes());
+                out.push(chunk[0]);
+            } else {
+                out.extend_from_slice(&(chunk.len() as u16).to_be_bytes());
+                out.extend_from_slice(chunk);
+            }
+            start = end;
+            if start == IPS_EOF_OFFSET && start < range.end {
+                start -= 1;
+            }
+        }
+    }
+    out.extend_from_slice(IPS_EOF);
+    if patched.len() < original.len() {
+        ensure!(
+            patched.len() <= IPS_MAX_OFFSET,
+            "IPS cannot truncate to 0x{:X}",
+            patched.len()
+        );
+        out.extend_from_slice(&(patched.len() as u32).to_be_bytes()[1..]);
+    }
+    Ok(out)
+}
+
+fn decode_ips(data: &[u8]) -> Result<Patch> {
+    let mut reader = Reader::new(&data[IPS_MAGIC.len()..]);
+    let mut records = Vec::new();
+    loop {
+        let head = reader
+            .take(3)
+            .context("IPS patch is missing its EOF marker")?;
+        if head == IPS_EOF {
+            break;
+        }
+        let offset = u32::from_be_bytes([0, head[0], head[1], head[2]]) as usize;
+        let size = reader.u16_be().context("IPS record is truncated")? as usize;
+        let bytes = if size == 0 {
+            let run = reader.u16_be().context("IPS RLE record is truncated")? as usize;
+            let value = reader.take(1).context("IPS RLE record is truncated")?[0];
+            vec![value; run]
+        } else {
+            reader
+                .take(size)
+                .context("IPS record is truncated")?
+                .to_vec()
+        };
+        records.push((offset, bytes));
+    }
+    let truncate = reader
+        .take(3)
+        .map(|size| u32::from_be_bytes([0, size[0], size[1], size[2]]) as usize);
+    Ok(Patch::Ips { records, truncate })
+}
+
+fn apply_ips(
+    records: &[(usize, Vec<u8>)],
+    truncate: Option<usize>,
+    source: &[u8],
+) -> Result<Vec<u8>> {
+    let mut out = source.to_vec();
+    for (offset, bytes) in records {
+        let end = offset + bytes.len();
+        if end > out.len() {
+            out.resize(end, 0);
+        }
+        out[*offset..end].copy_from_slice(bytes);
+    }
+    if let Some(size) = truncate {
+        out.truncate(size);
+    }
+    Ok(out)
+}
+
+fn encode_bps(original: &[u8], patched: &[u8]) -> Vec<u8> {
+    let mut out = BPS_MAGIC.to_vec();
+    write_varint(&mut out, original.len() as u64);
+    write_varint(&mut out, patched.len() as u64);
+    write_varint(&mut out, 0);
+    for op in diff::compare(original, patched) {
+        let len = op.right.len() as u64;
+        match op.kind {
+            DiffKind::Equal => write_varint(&mut out, (len - 1) << 2),
+            DiffKind::Replace | DiffKind::Insert => {
+                write_varint(&mut out, ((len - 1) << 2) | 1);
+                out.extend_from_slice(&patched[op.right]);
+            }
+            DiffKind::Delete => {}
+        }
+    }
+    out.extend_from_slice(&crc32(original).to_le_bytes());
+    out.extend_from_slice(&crc32(patched).to_le_bytes());
+    let patch_crc = crc32(&out);
+    out.extend_from_slice(&patch_crc.to_le_bytes());
+    out
+}
+
+fn apply_bps(data: &[u8], source: &[u8]) -> Result<Vec<u8>> {
+    let footer = &data[data.len() - 12..];
+    let source_crc = read_u32_le(&footer[0..4]);
+    let target_crc = read_u32_le(&footer[4..8]);
+
+    let mut reader = Reader::new(&data[BPS_MAGIC.len()..data.len() - 12]);
+    let source_size = reader.varint()? as usize;
+    let target_size = reader.varint()? as usize;
+    let metadata_size = reader.varint()? as usize;
+    reader
+        .take(metadata_size)
+        .context("BPS metadata is truncated")?;
+
+    ensure!(
+        source.len() == source_size,
+        "Source size does not match: patch expects {source_size} bytes, file has {}",
+        source.len()
+    );
+    ensure!(
+        crc32(source) == source_crc,
+        "Source checksum does not match; the patch was made for a different file"
+    );
+
+    // The sizes come from the patch, so only trust them as far as the data backs them.
+    let mut out = Vec::with_capacity(target_size.min(source.len() + data.len()));
+    let (mut source_rel, mut target_rel) = (0isize, 0isize);
+    while !reader.is_empty() {
+        let action = reader.varint()?;
+        let len = usize::try_from(action >> 2)
+            .ok()
+            .and_then(|len| len.checked_add(1))
+            .filter(|len| *len <= target_size - out.len())
+            .context("BPS action writes past the target size")?;
+        match action & 3 {
+            0 => {
+                let start = out.len();
+                let chunk = source
+                    .get(start..start + len)
+                    .context("BPS SourceRead is out of range")?;
+                out.extend_from_slice(chunk);
+            }
+            1 => out.extend_from_slice(reader.take(len).context("BPS TargetRead is truncated")?),
+            2 => {
+                source_rel = source_rel
+                    .checked_add(read_signed(&mut reader)?)
+                    .context("BPS SourceCopy is out of range")?;
+                let start =
+                    usize::try_from(source_rel).context("BPS SourceCopy is out of range")?;
+                let chunk = start
+                    .checked_add(len)
+                    .and_then(|end| source.get(start..end))
+                    .context("BPS SourceCopy is out of range")?;
+                out.extend_from_slice(chunk);
+                source_rel = isize::try_from(len)
+                    .ok()
+                    .and_then(|len| source_rel.checked_add(len))
+                    .context("BPS SourceCopy is out of range")?;
+            }
+            _ => {
+                target_rel = target_rel
+                    .checked_add(read_signed(&mut reader)?)
+                    .context("BPS TargetCopy is out of range")?;
+                let start =
+                    usize::try_from(target_rel).context("BPS TargetCopy is out of range")?;
+                ensure!(start < out.len(), "BPS TargetCopy is out of range");
+                // Byte by byte: the copy may overlap the bytes it produces.
+                for idx in start..start + len {
+                    let byte = *out.get(idx).context("BPS TargetCopy is out of range")?;
+                    out.push(byte);
+                }
+                target_rel = isize::try_from(len)
+                    .ok()
+                    .and_then(|len| target_rel.checked_add(len))
+                    .context("BPS TargetCopy is out of range")?;
+            }
+        }
+    }
+
+    ensure!(out.len() == target_size, "BPS output has the wrong size");
+    ensure!(
+        crc32(&out) == target_crc,
+        "BPS output checksum does not match"
+    );
+    Ok(out)
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let low = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(0x80 | low);
+            break;
+        }
+        out.push(low);
+        value -= 1;
+    }
+}
+
+fn read_signed(reader: &mut Reader) -> Result<isize> {
+    let value = reader.varint()?;
+    let magnitude = isize::try_from(value >> 1).context("BPS number is too large")?;
+    Ok(if value & 1 == 1 {
+        -magnitude
+    } else {
+        magnitude
+    })
+}
+
+fn read_u32_le(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// CRC-32 (IEEE), as used by BPS.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let chunk = self.data.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(chunk)
+    }
+
+    fn u16_be(&mut self) -> Option<u16> {
+        self.take(2)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let (mut value, mut shift) = (0u64, 1u64);
+        loop {
+            let byte = self.take(1).context("BPS number is truncated")?[0];
+            let digit = u64::from(byte & 0x7F)
+                .checked_mul(shift)
+                .context("BPS number is too large")?;
+            value = value
+                .checked_add(digit)
+                .context("BPS number is too large")?;
+            if byte & 0x80 != 0 {
+                return Ok(value);
+            }
+            // `checked_shl` only rejects shift amounts, not bits shifted out, so multiply.
+            shift = shift.checked_mul(128).context("BPS number is too large")?;
+            value = value
+                .checked_add(shift)
+                .context("BPS number is too large")?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(format: PatchFormat, original: &[u8], patched: &[u8]) {
+        let data = encode(format, original, patched).unwrap();
+        let patch = decode(&data).unwrap();
+        assert_eq!(patch.format(), format);
+        assert_eq!(patch.apply(original).unwrap(), patched, "{format:?}");
+    }
+
+    /// A BPS patch around `body`, with the checksums filled in so decoding accepts it.
+    fn bps(source: &[u8], target: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut out = BPS_MAGIC.to_vec();
+        out.extend_from_slice(body);
+        out.extend_from_slice(&crc32(source).to_le_bytes());
+        out.extend_from_slice(&crc32(target).to_le_bytes());
+        let patch_crc = crc32(&out);
+        out.extend_from_slice(&patch_crc.to_le_bytes());
+        out
+    }
+
+    #[test]
+    fn every_format_round_trips() {
+        let original: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let mut same_size = original.clone();
+        same_size[10] = 0xAA;
+        same_size[500..520].fill(0);
+        let mut grown = original.clone();
+        grown.extend_from_slice(b"tail");
+        let shrunk = original[..900].to_vec();
+
+        for format in [PatchFormat::Ips, PatchFormat::Bps, PatchFormat::Text] {
+            round_trip(format, &original, &same_size);
+            round_trip(format, &original, &grown);
+            round_trip(format, &original, &shrunk);
+            round_trip(format, &original, &original);
+            round_trip(format, b"", b"new");
+        }
+    }
+
+    #[test]
+    fn ips_steps_around_the_eof_marker_offset() {
+        let original = vec![0u8; IPS_EOF_OFFSET + 16];
+        let mut patched = original.clone();
+        patched[IPS_EOF_OFFSET] = 1;
+        round_trip(PatchFormat::Ips, &original, &patched);
+    }
+
+    #[test]
+    fn text_patches_check_the_original_bytes() {
+        let patch = decode(b"0x00000001: 22 -> 33\n").unwrap();
+        assert_eq!(patch.apply(&[0x11, 0x22]).unwrap(), [0x11, 0x33]);
+        assert!(patch.apply(&[0x11, 0x44]).is_err());
+    }
+
+    #[test]
+    fn text_patches_reject_out_of_range_records() {
+        let huge = format!("0x{:X}: - -> -\n", usize::MAX);
+        assert!(decode(huge.as_bytes()).unwrap().apply(b"abc").is_err());
+        let past_end = decode(b"0x10: - -> 01\n").unwrap();
+        assert!(past_end.apply(b"abc").is_err());
+        assert!(decode(b"0x0: 61 62\n").is_err());
+    }
+
+    #[test]
+    fn text_records_must_not_change_the_length_mid_file() {
+        let apply = |patch: &[u8]| decode(patch).unwrap().apply(b"abcdef");
+        let error = apply(b"0x1: 62 63 64 -> 58\n").unwrap_err();
+        assert!(error.to_string().contains("truncate:"), "{error}");
+        assert!(apply(b"0x4: 65 66 -> -\n").is_err());
+        assert!(apply(b"0x1: 62 -> 58 59\n").is_err());
+        assert_eq!(apply(b"0x4: 65 66 -> 45 46 47\n").unwrap(), b"abcdEFG");
+        assert_eq!(apply(b"0x1: 62 -> 58\ntruncate: 0x3\n").unwrap(), b"aXc");
+    }
+
+    #[test]
+    fn bps_rejects_a_different_source() {
+        let data = encode(PatchFormat::Bps, b"abcd", b"abXd").unwrap();
+        let patch = decode(&data).unwrap();
+        assert!(patch.apply(b"abce").is_err());
+        assert!(patch.apply(b"abc").is_err());
+    }
+
+    #[test]
+    fn bps_rejects_corrupt_patches() {
+        let mut data = encode(PatchFormat::Bps, b"abcd", b"abXd").unwrap();
+        data[6] ^= 1;
+        assert!(decode(&data).is_err());
+        assert!(decode(b"BPS1").is_err());
+    }
+
+    #[test]
+    fn bps_numbers_that_overflow_are_errors() {
+        // Ten continuation bytes run past 64 bits; the last one ends the number.
+        let mut body = vec![0x7F; 10];
+        body.push(0xFF);
+        let data = bps(b"", b"", &body);
+        assert!(decode(&data).unwrap().apply(b"").is_err());
+    }
+
+    #[test]
+    fn bps_actions_cannot_outgrow_the_target() {
+        let mut body = Vec::new();
+        write_varint(&mut body, 0);
+        write_varint(&mut body, 1);
+        write_varint(&mut body, 0);
+        // TargetRead of u64::MAX >> 2 bytes.
+        write_varint(&mut body, u64::MAX - 2);
+        let data = bps(b"", b"x", &body);
+        assert!(decode(&data).unwrap().apply(b"").is_err());
+    }
+
+    #[test]
+    fn varints_round_trip() {
+        for value in [
+            0,
+            1,
+            127,
+            128,
+            16_511,
+            16_512,
+            u32::MAX as u64,
+            u64::MAX >> 1,
+        ] {
+            let mut out = Vec::new();
+            write_varint(&mut out, value);
+            assert_eq!(Reader::new(&out).varint().unwrap(), value);
+        }
+    }
+}
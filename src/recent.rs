@@ -0,0 +1,65 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+
+/// How many files the GUI's File > Open Recent menu remembers.
+const MAX_RECENT: usize = 10;
+
+/// Files opened in the GUI, most recent first, kept between runs.
+pub struct RecentFiles {
+    paths: Vec<PathBuf>,
+    /// Where the list is saved; `None` when the platform has no data directory.
+    file: Option<PathBuf>,
+}
+
+/// `~/.local/share/rust-hex-viewer/recent.json`, or the platform's equivalent.
+fn default_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("rust-hex-viewer").join("recent.json"))
+}
+
+impl RecentFiles {
+    /// Read the saved list. A missing or unreadable list starts out empty.
+    pub fn load() -> Self {
+        let file = default_path();
+        let paths = file
+            .as_deref()
+            .and_then(|file| fs::read(file).ok())
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+        Self { paths, file }
+    }
+
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Move `path` to the top of the list and save it.
+    pub fn add(&mut self, path: &Path) -> Result<()> {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        self.paths.retain(|recent| *recent != path);
+        self.paths.insert(0, path);
+        self.paths.truncate(MAX_RECENT);
+        self.save()
+    }
+
+    pub fn clear(&mut self) -> Result<()> {
+        self.paths.clear();
+        self.save()
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let data = serde_json::to_vec_pretty(&self.paths)?;
+        fs::write(file, data)
+            .with_context(|| format!("Failed to save recent files to {}", file.display()))
+    }
+}
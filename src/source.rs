@@ -0,0 +1,164 @@
+use std::{
+    fs::{self, File, Metadata},
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Data arriving from a background reader.
+pub enum Chunk {
+    Data(Vec<u8>),
+    Error(io::Error),
+}
+
+/// Read `reader` on a background thread, forwarding data as it arrives.
+///
+/// The first `skip` bytes are discarded and at most `limit` bytes are forwarded. The
+/// channel disconnects once the reader hits end of input (or the limit) or fails.
+pub fn spawn_reader(
+    reader: impl Read + Send + 'static,
+    skip: u64,
+    limit: Option<u64>,
+) -> Receiver<Chunk> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut reader = reader;
+        if let Err(err) = io::copy(&mut (&mut reader).take(skip), &mut io::sink()) {
+            let _ = tx.send(Chunk::Error(err));
+            return;
+        }
+        let mut reader = reader.take(limit.unwrap_or(u64::MAX));
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    if tx.send(Chunk::Data(buf[..n].to_vec())).is_err() {
+                        break;
+                    }
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    let _ = tx.send(Chunk::Error(err));
+                    break;
+                }
+            }
+        }
+    });
+    rx
+}
+
+/// What a [`Follower`] found when polling.
+pub enum Followed {
+    /// Data appended since the last poll; empty if nothing changed.
+    Appended(Vec<u8>),
+    /// The file shrank or another file took its place, so the loaded bytes are stale.
+    Replaced,
+}
+
+/// Polls a growing file for data appended after `position`.
+pub struct Follower {
+    path: PathBuf,
+    file: File,
+    position: u64,
+}
+
+impl Follower {
+    pub fn open(path: &Path, position: u64) -> io::Result<Self> {
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: File::open(path)?,
+            position,
+        })
+    }
+
+    /// Reopen the path, which may now be a different file, and continue from `position`.
+    pub fn restart(&mut self, position: u64) -> io::Result<()> {
+        self.file = File::open(&self.path)?;
+        self.position = position;
+        Ok(())
+    }
+
+    /// Read whatever was appended since the last poll, unless the file was replaced.
+    pub fn poll(&mut self) -> io::Result<Followed> {
+        let current = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata,
+            // Briefly missing while another program replaces it.
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return Ok(Followed::Appended(Vec::new()));
+            }
+            Err(err) => return Err(err),
+        };
+        let opened = self.file.metadata()?;
+        if !same_file(&opened, &current) || opened.len() < self.position {
+            return Ok(Followed::Replaced);
+        }
+        let len = opened.len();
+        let mut data = Vec::new();
+        if len > self.position {
+            self.file.seek(SeekFrom::Start(self.position))?;
+            (&mut self.file)
+                .take(len - self.position)
+                .read_to_end(&mut data)?;
+            self.position += data.len() as u64;
+        }
+        Ok(Followed::Appended(data))
+    }
+}
+
+#[cfg(unix)]
+fn same_file(a: &Metadata, b: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    (a.dev(), a.ino()) == (b.dev(), b.ino())
+}
+
+/// Without inode numbers, a file replaced by one of the same size goes unnoticed.
+#[cfg(not(unix))]
+fn same_file(a: &Metadata, b: &Metadata) -> bool {
+    a.len() == b.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn appended(follower: &mut Follower) -> Vec<u8> {
+        match follower.poll().unwrap() {
+            Followed::Appended(data) => data,
+            Followed::Replaced => panic!("unexpected replacement"),
+        }
+    }
+
+    #[test]
+    fn follower_reads_appends_and_notices_replacement() {
+        let dir = std::env::temp_dir().join(format!("rhv-follow-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("log.bin");
+        fs::write(&path, b"abc").unwrap();
+
+        let mut follower = Follower::open(&path, 3).unwrap();
+        assert!(appended(&mut follower).is_empty());
+        fs::write(&path, b"abcdef").unwrap();
+        assert_eq!(appended(&mut follower), b"def");
+
+        // Truncated in place.
+        fs::write(&path, b"ab").unwrap();
+        assert!(matches!(follower.poll().unwrap(), Followed::Replaced));
+        follower.restart(2).unwrap();
+        assert!(appended(&mut follower).is_empty());
+
+        // Replaced by renaming another file over it, as saving does.
+        let other = dir.join("log.tmp");
+        fs::write(&other, b"xyz123").unwrap();
+        fs::rename(&other, &path).unwrap();
+        assert!(matches!(follower.poll().unwrap(), Followed::Replaced));
+        follower.restart(6).unwrap();
+        fs::write(&path, b"xyz1234").unwrap();
+        assert_eq!(appended(&mut follower), b"4");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
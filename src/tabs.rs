@@ -0,0 +1,60 @@
+/// Open buffers shown as tabs, with one of them active.
+pub struct Tabs<T> {
+    items: Vec<T>,
+    active: usize,
+}
+
+impl<T> Tabs<T> {
+    /// Panics if `items` is empty; there is always at least one tab.
+    pub fn new(items: Vec<T>) -> Self {
+        assert!(!items.is_empty(), "at least one tab is required");
+        Self { items, active: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn active_index(&self) -> usize {
+        self.active
+    }
+
+    pub fn active(&self) -> &T {
+        &self.items[self.active]
+    }
+
+    pub fn active_mut(&mut self) -> &mut T {
+        &mut self.items[self.active]
+    }
+
+    /// Switch to the tab at `index`; out-of-range indices are ignored.
+    pub fn select(&mut self, index: usize) {
+        if index < self.items.len() {
+            self.active = index;
+        }
+    }
+
+    /// Move `delta` tabs forward (or back if negative), wrapping around.
+    pub fn cycle(&mut self, delta: isize) {
+        let len = self.items.len() as isize;
+        self.active = (self.active as isize + delta).rem_euclid(len) as usize;
+    }
+
+    /// Add a tab at the end and switch to it.
+    pub fn push(&mut self, item: T) {
+        self.items.push(item);
+        self.active = self.items.len() - 1;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.items.iter()
+    }
+
+    pub fn into_iter(self) -> impl Iterator<Item = T> {
+        self.items.into_iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.items.iter_mut()
+    }
+}
@@ -0,0 +1,1043 @@
+use std::{fmt::Write as FmtWrite, fs, ops::Range, path::Path};
+
+use anyhow::{Context, Result, bail, ensure};
+
+/// Nested structs deeper than this are assumed to recurse forever.
+const MAX_DEPTH: usize = 32;
+/// Upper bound on fields produced by one template, so a bad count cannot hang the viewer.
+const MAX_FIELDS: usize = 20_000;
+/// Elements of a number array shown in its value before it is cut short.
+const PREVIEW_ELEMENTS: usize = 8;
+
+/// A parsed template: struct and enum definitions, the last struct being the one applied.
+#[derive(Clone, Debug)]
+pub struct Template {
+    structs: Vec<StructDef>,
+    enums: Vec<EnumDef>,
+    endian: Endian,
+}
+
+#[derive(Clone, Debug)]
+struct StructDef {
+    name: String,
+    members: Vec<Member>,
+}
+
+#[derive(Clone, Debug)]
+enum Member {
+    Field(FieldDef),
+    If {
+        condition: Expr,
+        then: Vec<Member>,
+        otherwise: Vec<Member>,
+    },
+}
+
+#[derive(Clone, Debug)]
+struct FieldDef {
+    name: String,
+    ty: String,
+    endian: Option<Endian>,
+    /// Element count for arrays, often an earlier field for length-prefixed data.
+    count: Option<Expr>,
+}
+
+#[derive(Clone, Debug)]
+struct EnumDef {
+    name: String,
+    base: Prim,
+    variants: Vec<(String, i128)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Endian {
+    Little,
+    Big,
+}
+
+/// Built-in number types.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Prim {
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+    /// A byte shown as a character; arrays of them are shown as a string.
+    Char,
+}
+
+impl Prim {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "u8" => Prim::U8,
+            "u16" => Prim::U16,
+            "u32" => Prim::U32,
+            "u64" => Prim::U64,
+            "i8" => Prim::I8,
+            "i16" => Prim::I16,
+            "i32" => Prim::I32,
+            "i64" => Prim::I64,
+            "char" => Prim::Char,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Prim::U8 | Prim::I8 | Prim::Char => 1,
+            Prim::U16 | Prim::I16 => 2,
+            Prim::U32 | Prim::I32 => 4,
+            Prim::U64 | Prim::I64 => 8,
+        }
+    }
+
+    fn read(self, bytes: &[u8], endian: Endian) -> i128 {
+        let mut raw = [0u8; 8];
+        match endian {
+            Endian::Little => raw[..bytes.len()].copy_from_slice(bytes),
+            Endian::Big => {
+                for (slot, byte) in raw.iter_mut().zip(bytes.iter().rev()) {
+                    *slot = *byte;
+                }
+            }
+        }
+        let value = u64::from_le_bytes(raw);
+        match self {
+            Prim::I8 => value as i8 as i128,
+            Prim::I16 => value as i16 as i128,
+            Prim::I32 => value as i32 as i128,
+            Prim::I64 => value as i64 as i128,
+            _ => value as i128,
+        }
+    }
+
+    fn format(self, value: i128) -> String {
+        match self {
+            Prim::Char => format!("'{}'", char_text(value as u8)),
+            _ if value >= 10 => format!("{value} (0x{value:X})"),
+            _ => value.to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    Number(i128),
+    Name(String),
+    Not(Box<Expr>),
+    Negate(Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum BinOp {
+    Or,
+    And,
+    BitOr,
+    BitAnd,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+/// Operators by precedence, loosest first.
+const PRECEDENCE: &[&[(&str, BinOp)]] = &[
+    &[("||", BinOp::Or)],
+    &[("&&", BinOp::And)],
+    &[("|", BinOp::BitOr)],
+    &[("&", BinOp::BitAnd)],
+    &[("==", BinOp::Eq), ("!=", BinOp::Ne)],
+    &[
+        ("<=", BinOp::Le),
+        (">=", BinOp::Ge),
+        ("<", BinOp::Lt),
+        (">", BinOp::Gt),
+    ],
+    &[("+", BinOp::Add), ("-", BinOp::Sub)],
+    &[("*", BinOp::Mul), ("/", BinOp::Div), ("%", BinOp::Rem)],
+];
+
+/// One field of an applied template and the bytes it covers.
+#[derive(Clone, Debug)]
+pub struct Field {
+    pub name: String,
+    /// Buffer indexes the field was read from.
+    pub range: Range<usize>,
+    /// The value as text; empty for structs and struct arrays, which have children.
+    pub value: String,
+    pub children: Vec<Field>,
+}
+
+/// A field as one line of a flattened tree, for drawing it as a list.
+pub struct FieldLine<'a> {
+    pub depth: usize,
+    pub field: &'a Field,
+}
+
+impl Field {
+    /// This field and all fields below it, depth first.
+    pub fn lines(&self) -> Vec<FieldLine<'_>> {
+        let mut lines = Vec::new();
+        self.push_lines(0, &mut lines);
+        lines
+    }
+
+    fn push_lines<'a>(&'a self, depth: usize, lines: &mut Vec<FieldLine<'a>>) {
+        lines.push(FieldLine { depth, field: self });
+        for child in &self.children {
+            child.push_lines(depth + 1, lines);
+        }
+    }
+
+    /// Ranges of the fields without children, in order.
+    pub fn leaf_ranges(&self) -> Vec<Range<usize>> {
+        let mut ranges = Vec::new();
+        self.push_leaves(&mut ranges);
+        ranges
+    }
+
+    fn push_leaves(&self, ranges: &mut Vec<Range<usize>>) {
+        if self.children.is_empty() {
+            if !self.range.is_empty() {
+                ranges.push(self.range.clone());
+            }
+        } else {
+            for child in &self.children {
+                child.push_leaves(ranges);
+            }
+        }
+    }
+}
+
+impl Template {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read template {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Invalid template {}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+        };
+        let mut template = Template {
+            structs: Vec::new(),
+            enums: Vec::new(),
+            endian: Endian::Little,
+        };
+        while !parser.at_end() {
+            parser.item(&mut template)?;
+        }
+        template.check()?;
+        Ok(template)
+    }
+
+    /// Read the last struct in the template from `data`, starting at index `start`.
+    pub fn apply(&self, data: &[u8], start: usize) -> Result<Field> {
+        let root = self.structs.last().context("Template has no struct")?;
+        let mut reader = Reader {
+            template: self,
+            data,
+            position: start,
+            fields: 0,
+            scopes: Vec::new(),
+        };
+        reader.read_struct(root, root.name.clone(), 0)
+    }
+
+    /// Make sure every field has a known type and names are not defined twice.
+    fn check(&self) -> Result<()> {
+        let mut names: Vec<&str> = Vec::new();
+        for name in self
+            .structs
+            .iter()
+            .map(|def| def.name.as_str())
+            .chain(self.enums.iter().map(|def| def.name.as_str()))
+        {
+            ensure!(!names.contains(&name), "`{name}` is defined twice");
+            ensure!(
+                Prim::from_name(name).is_none(),
+                "`{name}` is a built-in type name"
+            );
+            names.push(name);
+        }
+        for def in &self.structs {
+            check_members(&def.members, &names)
+                .with_context(|| format!("In struct `{}`", def.name))?;
+        }
+        Ok(())
+    }
+
+    fn enum_def(&self, name: &str) -> Option<&EnumDef> {
+        self.enums.iter().find(|def| def.name == name)
+    }
+
+    fn struct_def(&self, name: &str) -> Option<&StructDef> {
+        self.structs.iter().find(|def| def.name == name)
+    }
+}
+
+fn check_members(members: &[Member], types: &[&str]) -> Result<()> {
+    for member in members {
+        match member {
+            Member::Field(field) => ensure!(
+                Prim::from_name(&field.ty).is_some() || types.contains(&field.ty.as_str()),
+                "Unknown type `{}` for field `{}`",
+                field.ty,
+                field.name
+            ),
+            Member::If {
+                then, otherwise, ..
+            } => {
+                check_members(then, types)?;
+                check_members(otherwise, types)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(i128),
+    Punct(&'static str),
+}
+
+/// Punctuation, longest first so `<=` wins over `<`.
+const PUNCTUATION: &[&str] = &[
+    "==", "!=", "<=", ">=", "&&", "||", "{", "}", "(", ")", "[", "]", ";", ":", ",", "=", "<", ">",
+    "+", "-", "*", "/", "%", "&", "|", "!",
+];
+
+/// Split template text into tokens, each with its line number.
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>> {
+    let mut tokens = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        let line_number = idx + 1;
+        let line = line.split_once("//").map_or(line, |(code, _)| code);
+        let mut rest = line.trim_start();
+        while !rest.is_empty() {
+            let token;
+            if rest.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+                let end = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+                token = Token::Ident(rest[..end].to_string());
+                rest = &rest[end..];
+            } else if rest.starts_with(|c: char| c.is_ascii_digit()) {
+                let end = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+                let text = rest[..end].replace('_', "");
+                let number = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+                    Some(hex) => i128::from_str_radix(hex, 16),
+                    None => text.parse(),
+                };
+                let number = number
+                    .map_err(|_| anyhow::anyhow!("Line {line_number}: bad number `{text}`"))?;
+                token = Token::Number(number);
+                rest = &rest[end..];
+            } else if let Some(punct) = PUNCTUATION.iter().find(|punct| rest.starts_with(*punct)) {
+                token = Token::Punct(punct);
+                rest = &rest[punct.len()..];
+            } else {
+                let c = rest.chars().next().unwrap_or_default();
+                bail!("Line {line_number}: unexpected `{c}`");
+            }
+            tokens.push((token, line_number));
+            rest = rest.trim_start();
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+}
+
+impl Parser {
+    fn at_end(&self) -> bool {
+        self.position >= self.tokens.len()
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    /// Line of the next token, or of the last one at the end of the input.
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .or(self.tokens.last())
+            .map_or(1, |(_, line)| *line)
+    }
+
+    fn error(&self, expected: &str) -> anyhow::Error {
+        let found = match self.peek() {
+            Some(Token::Ident(name)) => format!("`{name}`"),
+            Some(Token::Number(number)) => format!("`{number}`"),
+            Some(Token::Punct(punct)) => format!("`{punct}`"),
+            None => "the end of the file".to_string(),
+        };
+        anyhow::anyhow!("Line {}: expected {expected}, found {found}", self.line())
+    }
+
+    fn eat(&mut self, punct: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Punct(found)) if *found == punct) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, punct: &str) -> Result<()> {
+        if self.eat(punct) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("`{punct}`")))
+        }
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Ident(name)) if name == keyword) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn ident(&mut self, what: &str) -> Result<String> {
+        match self.peek() {
+            Some(Token::Ident(name)) => {
+                let name = name.clone();
+                self.position += 1;
+                Ok(name)
+            }
+            _ => Err(self.error(what)),
+        }
+    }
+
+    fn item(&mut self, template: &mut Template) -> Result<()> {
+        if self.eat_keyword("endian") {
+            template.endian = self
+                .endian()?
+                .ok_or_else(|| self.error("`little` or `big`"))?;
+            self.expect(";")
+        } else if self.eat_keyword("enum") {
+            let name = self.ident("an enum name")?;
+            self.expect(":")?;
+            let base = self.ident("a number type")?;
+            let base = Prim::from_name(&base)
+                .filter(|prim| *prim != Prim::Char)
+                .ok_or_else(|| {
+                    self.position -= 1;
+                    self.error("a number type such as `u8`")
+                })?;
+            self.expect("{")?;
+            let mut variants = Vec::new();
+            let mut next = 0;
+            while !self.eat("}") {
+                let variant = self.ident("a variant name")?;
+                if self.eat("=") {
+                    next = self.constant()?;
+                }
+                variants.push((variant, next));
+                next = next.checked_add(1).ok_or_else(|| {
+                    anyhow::anyhow!("Line {}: enum value out of range", self.line())
+                })?;
+                if !self.eat(",") {
+                    self.expect("}")?;
+                    break;
+                }
+            }
+            self.eat(";");
+            template.enums.push(EnumDef {
+                name,
+                base,
+                variants,
+            });
+            Ok(())
+        } else if self.eat_keyword("struct") {
+            let name = self.ident("a struct name")?;
+            self.expect("{")?;
+            let members = self.members()?;
+            self.eat(";");
+            template.structs.push(StructDef { name, members });
+            Ok(())
+        } else {
+            Err(self.error("`struct`, `enum` or `endian`"))
+        }
+    }
+
+    fn endian(&mut self) -> Result<Option<Endian>> {
+        Ok(if self.eat_keyword("little") || self.eat_keyword("le") {
+            Some(Endian::Little)
+        } else if self.eat_keyword("big") || self.eat_keyword("be") {
+            Some(Endian::Big)
+        } else {
+            None
+        })
+    }
+
+    /// Members up to and including the closing `}`.
+    fn members(&mut self) -> Result<Vec<Member>> {
+        let mut members = Vec::new();
+        while !self.eat("}") {
+            if self.at_end() {
+                return Err(self.error("`}`"));
+            }
+            members.push(self.member()?);
+        }
+        Ok(members)
+    }
+
+    fn member(&mut self) -> Result<Member> {
+        if self.eat_keyword("if") {
+            self.expect("(")?;
+            let condition = self.expr()?;
+            self.expect(")")?;
+            let then = self.block()?;
+            let otherwise = if self.eat_keyword("else") {
+                self.block()?
+            } else {
+                Vec::new()
+            };
+            return Ok(Member::If {
+                condition,
+                then,
+                otherwise,
+            });
+        }
+        let endian = self.endian()?;
+        let ty = self.ident("a field type")?;
+        let name = self.ident("a field name")?;
+        let count = if self.eat("[") {
+            let count = self.expr()?;
+            self.expect("]")?;
+            Some(count)
+        } else {
+            None
+        };
+        self.expect(";")?;
+        Ok(Member::Field(FieldDef {
+            name,
+            ty,
+            endian,
+            count,
+        }))
+    }
+
+    /// A `{ ... }` block, or a single member.
+    fn block(&mut self) -> Result<Vec<Member>> {
+        if self.eat("{") {
+            self.members()
+        } else {
+            Ok(vec![self.member()?])
+        }
+    }
+
+    /// A number, possibly negative, for enum values.
+    fn constant(&mut self) -> Result<i128> {
+        let negative = self.eat("-");
+        match self.peek() {
+            Some(Token::Number(number)) => {
+                let number = *number;
+                self.position += 1;
+                Ok(if negative { -number } else { number })
+            }
+            _ => Err(self.error("a number")),
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        self.binary(0)
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr> {
+        let Some(operators) = PRECEDENCE.get(level) else {
+            return self.unary();
+        };
+        let mut left = self.binary(level + 1)?;
+        'outer: loop {
+            for (punct, op) in *operators {
+                if self.eat(punct) {
+                    let right = self.binary(level + 1)?;
+                    left = Expr::Binary(*op, Box::new(left), Box::new(right));
+                    continue 'outer;
+                }
+            }
+            return Ok(left);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.eat("-") {
+            return Ok(Expr::Negate(Box::new(self.unary()?)));
+        }
+        if self.eat("(") {
+            let expr = self.expr()?;
+            self.expect(")")?;
+            return Ok(expr);
+        }
+        match self.peek().cloned() {
+            Some(Token::Number(number)) => {
+                self.position += 1;
+                Ok(Expr::Number(number))
+            }
+            Some(Token::Ident(name)) => {
+                self.position += 1;
+                Ok(Expr::Name(name))
+            }
+            _ => Err(self.error("a value")),
+        }
+    }
+}
+
+/// Walks the data while applying a template.
+struct Reader<'a> {
+    template: &'a Template,
+    data: &'a [u8],
+    position: usize,
+    fields: usize,
+    /// Values of the number fields read so far in each enclosing struct, innermost last.
+    scopes: Vec<Vec<(String, i128)>>,
+}
+
+impl Reader<'_> {
+    fn read_struct(&mut self, def: &StructDef, name: String, depth: usize) -> Result<Field> {
+        ensure!(
+            depth < MAX_DEPTH,
+            "Structs nest more than {MAX_DEPTH} deep at `{name}`"
+        );
+        let start = self.position;
+        self.scopes.push(Vec::new());
+        let mut children = Vec::new();
+        let result = self.read_members(&def.members, depth, &mut children);
+        self.scopes.pop();
+        result?;
+        Ok(Field {
+            name,
+            range: start..self.position,
+            value: String::new(),
+            children,
+        })
+    }
+
+    fn read_members(
+        &mut self,
+        members: &[Member],
+        depth: usize,
+        children: &mut Vec<Field>,
+    ) -> Result<()> {
+        for member in members {
+            match member {
+                Member::Field(def) => {
+                    let field = self.read_field(def, depth)?;
+                    children.push(field);
+                }
+                Member::If {
+                    condition,
+                    then,
+                    otherwise,
+                } => {
+                    let branch = if self.eval(condition)? != 0 {
+                        then
+                    } else {
+                        otherwise
+                    };
+                    self.read_members(branch, depth, children)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn read_field(&mut self, def: &FieldDef, depth: usize) -> Result<Field> {
+        self.fields += 1;
+        ensure!(
+            self.fields <= MAX_FIELDS,
+            "Template produced more than {MAX_FIELDS} fields"
+        );
+        let count = match &def.count {
+            Some(count) => {
+                let count = self.eval(count)?;
+                Some(usize::try_from(count).ok().with_context(|| {
+                    format!("Field `{}` has a negative count ({count})", def.name)
+                })?)
+            }
+            None => None,
+        };
+        let start = self.position;
+        let template = self.template;
+
+        if let Some(def_struct) = template.struct_def(&def.ty) {
+            let Some(count) = count else {
+                return self.read_struct(def_struct, def.name.clone(), depth + 1);
+            };
+            let mut children = Vec::new();
+            for idx in 0..count {
+                self.fields += 1;
+                ensure!(
+                    self.fields <= MAX_FIELDS,
+                    "Template produced more than {MAX_FIELDS} fields"
+                );
+                children.push(self.read_struct(def_struct, format!("[{idx}]"), depth + 1)?);
+            }
+            return Ok(Field {
+                name: def.name.clone(),
+                range: start..self.position,
+                value: format!("{count} items"),
+                children,
+            });
+        }
+
+        let enum_def = template.enum_def(&def.ty);
+        let prim = match enum_def {
+            Some(enum_def) => enum_def.base,
+            None => Prim::from_name(&def.ty).context("Unknown type")?,
+        };
+        let endian = def.endian.unwrap_or(template.endian);
+        let elements = count.unwrap_or(1);
+        let size = prim
+            .size()
+            .checked_mul(elements)
+            .filter(|size| start.checked_add(*size).is_some())
+            .with_context(|| format!("Field `{}` is too large", def.name))?;
+        ensure!(
+            start + size <= self.data.len(),
+            "Field `{}` at index {start:#x} needs {size} bytes, but the data ends at {:#x}",
+            def.name,
+            self.data.len()
+        );
+        let bytes = &self.data[start..start + size];
+        self.position = start + size;
+        let values: Vec<i128> = bytes
+            .chunks(prim.size())
+            .map(|chunk| prim.read(chunk, endian))
+            .collect();
+        let show = |value: i128| match enum_def {
+            Some(enum_def) => enum_def
+                .variants
+                .iter()
+                .find(|(_, variant)| *variant == value)
+                .map_or_else(
+                    || format!("{value} (unknown)"),
+                    |(name, _)| format!("{name} ({value})"),
+                ),
+            None => prim.format(value),
+        };
+
+        let value = match count {
+            None => {
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.push((def.name.clone(), values[0]));
+                }
+                show(values[0])
+            }
+            Some(_) if prim == Prim::Char => {
+                let text: String = bytes
+                    .iter()
+                    .take_while(|byte| **byte != 0)
+                    .map(|byte| char_text(*byte))
+                    .collect();
+                format!("\"{text}\"")
+            }
+            Some(_) if prim == Prim::U8 && enum_def.is_none() => {
+                let mut text = String::new();
+                for byte in bytes.iter().take(PREVIEW_ELEMENTS * 2) {
+                    let _ = write!(text, "{byte:02X} ");
+                }
+                if bytes.len() > PREVIEW_ELEMENTS * 2 {
+                    text.push_str("...");
+                }
+                text.trim_end().to_string()
+            }
+            Some(_) => {
+                let mut shown: Vec<String> = values
+                    .iter()
+                    .take(PREVIEW_ELEMENTS)
+                    .map(|value| show(*value))
+                    .collect();
+                if values.len() > PREVIEW_ELEMENTS {
+                    shown.push("...".to_string());
+                }
+                format!("[{}]", shown.join(", "))
+            }
+        };
+        Ok(Field {
+            name: def.name.clone(),
+            range: start..self.position,
+            value,
+            children: Vec::new(),
+        })
+    }
+
+    fn eval(&self, expr: &Expr) -> Result<i128> {
+        Ok(match expr {
+            Expr::Number(number) => *number,
+            Expr::Name(name) => self.lookup(name)?,
+            Expr::Not(inner) => (self.eval(inner)? == 0) as i128,
+            Expr::Negate(inner) => self.eval(inner)?.saturating_neg(),
+            Expr::Binary(op, left, right) => {
+                let left = self.eval(left)?;
+                // `&&` and `||` skip the right side like in C, so it can guard a division.
+                match op {
+                    BinOp::And if left == 0 => return Ok(0),
+                    BinOp::Or if left != 0 => return Ok(1),
+                    _ => {}
+                }
+                let right = self.eval(right)?;
+                match op {
+                    BinOp::Or | BinOp::And => (right != 0) as i128,
+                    BinOp::BitOr => left | right,
+                    BinOp::BitAnd => left & right,
+                    BinOp::Eq => (left == right) as i128,
+                    BinOp::Ne => (left != right) as i128,
+                    BinOp::Lt => (left < right) as i128,
+                    BinOp::Le => (left <= right) as i128,
+                    BinOp::Gt => (left > right) as i128,
+                    BinOp::Ge => (left >= right) as i128,
+                    BinOp::Add => left.saturating_add(right),
+                    BinOp::Sub => left.saturating_sub(right),
+                    BinOp::Mul => left.saturating_mul(right),
+                    BinOp::Div | BinOp::Rem if right == 0 => bail!("Division by zero"),
+                    BinOp::Div => left.saturating_div(right),
+                    // Unlike `checked_rem`, this gives 0 for `i128::MIN % -1`.
+                    BinOp::Rem => left.wrapping_rem(right),
+                }
+            }
+        })
+    }
+
+    /// A field read earlier, innermost struct first, or else an enum variant.
+    fn lookup(&self, name: &str) -> Result<i128> {
+        let field = self.scopes.iter().rev().find_map(|scope| {
+            scope
+                .iter()
+                .rev()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| *value)
+        });
+        let variant = || {
+            self.template.enums.iter().find_map(|def| {
+                def.variants
+                    .iter()
+                    .find(|(variant, _)| variant == name)
+                    .map(|(_, value)| *value)
+            })
+        };
+        field
+            .or_else(variant)
+            .with_context(|| format!("No field or enum value named `{name}` has been read"))
+    }
+}
+
+/// How a byte is shown inside a quoted string or character.
+fn char_text(byte: u8) -> String {
+    match byte {
+        b'"' => "\\\"".to_string(),
+        b'\\' => "\\\\".to_string(),
+        b' '..=b'~' => (byte as char).to_string(),
+        b'\n' => "\\n".to_string(),
+        b'\r' => "\\r".to_string(),
+        b'\t' => "\\t".to_string(),
+        _ => format!("\\x{byte:02X}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG: &str = r#"
+        endian big;
+        enum ColorType : u8 { Gray = 0, Rgb = 2, Rgba = 6 }
+        struct Header {
+            u32 width;
+            u32 height;
+            u8 bit_depth;
+            ColorType color_type;
+        }
+        struct Chunk {
+            u32 length;
+            char kind[4];
+            if (length == 10) { Header header; } else { u8 data[length]; }
+            be u32 crc;
+        }
+        struct Png {
+            u8 signature[8];
+            Chunk chunks[2];
+        }
+    "#;
+
+    fn png() -> Vec<u8> {
+        let mut data = b"\x89PNG\r\n\x1a\n".to_vec();
+        data.extend_from_slice(&[0, 0, 0, 10]);
+        data.extend_from_slice(b"IHDR");
+        data.extend_from_slice(&[0, 0, 1, 0, 0, 0, 0, 16, 8, 6]);
+        data.extend_from_slice(&[0xAA; 4]);
+        data.extend_from_slice(&[0, 0, 0, 3]);
+        data.extend_from_slice(b"tEXt");
+        data.extend_from_slice(b"abc");
+        data.extend_from_slice(&[0xBB; 4]);
+        data
+    }
+
+    fn child<'a>(field: &'a Field, path: &[&str]) -> &'a Field {
+        path.iter().fold(field, |field, name| {
+            field
+                .children
+                .iter()
+                .find(|child| child.name == *name)
+                .unwrap_or_else(|| panic!("no field {name} in {}", field.name))
+        })
+    }
+
+    /// Evaluate `text` with a field `x` of 5 in scope.
+    fn eval(text: &str) -> Result<i128> {
+        let template = Template::parse("enum E : u8 { A = 7 } struct S { u8 x; }")?;
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            position: 0,
+        };
+        let expr = parser.expr()?;
+        ensure!(parser.at_end(), "trailing tokens");
+        let reader = Reader {
+            template: &template,
+            data: &[],
+            position: 0,
+            fields: 0,
+            scopes: vec![vec![("x".to_string(), 5)]],
+        };
+        reader.eval(&expr)
+    }
+
+    #[test]
+    fn png_fields_follow_lengths_and_conditions() {
+        let data = png();
+        let root = Template::parse(PNG).unwrap().apply(&data, 0).unwrap();
+        assert_eq!(root.range, 0..data.len());
+        assert_eq!(
+            child(&root, &["signature"]).value,
+            "89 50 4E 47 0D 0A 1A 0A"
+        );
+
+        let header = child(&root, &["chunks", "[0]", "header"]);
+        assert_eq!(header.range, 16..26);
+        assert_eq!(child(header, &["width"]).value, "256 (0x100)");
+        assert_eq!(child(header, &["height"]).value, "16 (0x10)");
+        assert_eq!(child(header, &["color_type"]).value, "Rgba (6)");
+
+        let text = child(&root, &["chunks", "[1]"]);
+        assert_eq!(child(text, &["kind"]).value, "\"tEXt\"");
+        assert_eq!(child(text, &["data"]).range, 38..41);
+        assert_eq!(child(text, &["crc"]).range, 41..45);
+        // Everything but the root, `chunks`, the two chunks and `header` is a leaf.
+        assert_eq!(root.leaf_ranges().len(), root.lines().len() - 5);
+    }
+
+    #[test]
+    fn endianness_enums_and_arrays() {
+        let template = Template::parse(
+            "enum K : u16 { One = 1 }
+             struct S { u16 a; be u16 b; K k; K unknown; i8 neg; i16 words[2]; }",
+        )
+        .unwrap();
+        let data = [1, 2, 1, 2, 1, 0, 9, 0, 0xFF, 0xFE, 0xFF, 3, 0];
+        let root = template.apply(&data, 0).unwrap();
+        let values: Vec<&str> = root.children.iter().map(|f| f.value.as_str()).collect();
+        assert_eq!(
+            values,
+            [
+                "513 (0x201)",
+                "258 (0x102)",
+                "One (1)",
+                "9 (unknown)",
+                "-1",
+                "[-2, 3]"
+            ]
+        );
+        assert!(template.apply(&data, 1).is_err());
+    }
+
+    #[test]
+    fn expressions_follow_c_precedence() {
+        assert_eq!(eval("1 + 2 * 3").unwrap(), 7);
+        assert_eq!(eval("(1 + 2) * 3").unwrap(), 9);
+        assert_eq!(eval("x % 3 == 2 && !0").unwrap(), 1);
+        assert_eq!(eval("x - -x").unwrap(), 10);
+        assert_eq!(eval("A | 8").unwrap(), 15);
+        assert_eq!(eval("0x10 & 0x30 >= 0").unwrap(), 0);
+        assert_eq!(eval("0 && 1 / 0").unwrap(), 0);
+        assert!(eval("1 / 0").is_err());
+        assert!(eval("x % 0").is_err());
+        assert!(eval("y").is_err());
+    }
+
+    #[test]
+    fn arithmetic_saturates_instead_of_overflowing() {
+        let max = i128::MAX.to_string();
+        let min = format!("(0 - {max} - 1)");
+        assert_eq!(eval(&format!("{max} + 1")).unwrap(), i128::MAX);
+        assert_eq!(eval(&format!("-{min}")).unwrap(), i128::MAX);
+        assert_eq!(eval(&format!("{min} / -1")).unwrap(), i128::MAX);
+        assert_eq!(eval(&format!("{min} % -1")).unwrap(), 0);
+        assert_eq!(eval(&format!("{min} * 2")).unwrap(), i128::MIN);
+    }
+
+    #[test]
+    fn enum_values_past_the_maximum_are_rejected() {
+        let text = format!("enum E : u8 {{ A = {} }}", i128::MAX);
+        let error = format!("{:#}", Template::parse(&text).unwrap_err());
+        assert!(error.contains("enum value out of range"), "{error}");
+    }
+
+    #[test]
+    fn bad_templates_name_the_problem() {
+        let error = |text: &str| format!("{:#}", Template::parse(text).unwrap_err());
+        assert!(error("struct S {\n u8 a\n}").starts_with("Line 3: expected `;`"));
+        assert!(error("struct S { Missing m; }").contains("Missing"));
+        assert!(error("struct S { u8 a; } enum S : u8 { A = 1 }").contains("defined twice"));
+        assert!(error("struct u8 { u8 a; }").contains("u8"));
+        let empty = Template::parse("// nothing here").unwrap();
+        assert!(empty.apply(&[], 0).is_err());
+    }
+
+    #[test]
+    fn limits_stop_runaway_templates() {
+        let error = |text: &str, data: &[u8]| {
+            let template = Template::parse(text).unwrap();
+            format!("{:#}", template.apply(data, 0).unwrap_err())
+        };
+        let deep = error("struct B { u8 x; if (x == 0) B b; }", &[0; 64]);
+        assert!(deep.contains("nest more than 32"), "{deep}");
+        let many = error("struct T { u8 v; } struct S { T t[30000]; }", &[0; 30000]);
+        assert!(many.contains("more than 20000 fields"), "{many}");
+        let huge = error(
+            "struct S { u8 n; u64 big[n * 0x7fffffffffffffff]; }",
+            &[2, 0],
+        );
+        assert!(huge.contains("`big`"), "{huge}");
+    }
+}
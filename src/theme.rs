@@ -0,0 +1,360 @@
+use std::{env, str::FromStr};
+
+use serde::Deserialize;
+
+/// Kinds of bytes that get their own color.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ByteClass {
+    Null,
+    /// `0xFF`, typical of erased flash and padding.
+    Full,
+    Whitespace,
+    Printable,
+    Control,
+    /// Bytes above `0x7F`.
+    High,
+}
+
+impl ByteClass {
+    pub fn of(byte: u8) -> Self {
+        match byte {
+            0x00 => ByteClass::Null,
+            0xFF => ByteClass::Full,
+            b' ' | b'\t' | b'\n' | b'\r' | 0x0B | 0x0C => ByteClass::Whitespace,
+            _ if byte.is_ascii_graphic() => ByteClass::Printable,
+            _ if byte.is_ascii() => ByteClass::Control,
+            _ => ByteClass::High,
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "null" => ByteClass::Null,
+            "ff" => ByteClass::Full,
+            "whitespace" => ByteClass::Whitespace,
+            "printable" => ByteClass::Printable,
+            "control" => ByteClass::Control,
+            "high" => ByteClass::High,
+            _ => return None,
+        })
+    }
+}
+
+/// A 24-bit color, converted to each frontend's own color type when drawing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl TryFrom<String> for Rgb {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl FromStr for Rgb {
+    type Err = String;
+
+    /// Accepts `#rrggbb` or one of the basic color names.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if let Some(hex) = value.strip_prefix('#')
+            && hex.len() == 6
+            && let Ok(rgb) = u32::from_str_radix(hex, 16)
+        {
+            return Ok(Rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8));
+        }
+        Ok(match value.to_ascii_lowercase().as_str() {
+            "black" => Rgb(0x00, 0x00, 0x00),
+            "red" => Rgb(0xD7, 0x5F, 0x5F),
+            "green" => Rgb(0x87, 0xD7, 0x87),
+            "yellow" => Rgb(0xD7, 0xD7, 0x5F),
+            "blue" => Rgb(0x5F, 0x87, 0xD7),
+            "magenta" => Rgb(0xD7, 0x87, 0xD7),
+            "cyan" => Rgb(0x5F, 0xD7, 0xD7),
+            "white" => Rgb(0xFF, 0xFF, 0xFF),
+            "gray" | "grey" => Rgb(0xA8, 0xA8, 0xA8),
+            "darkgray" | "darkgrey" => Rgb(0x6C, 0x6C, 0x6C),
+            _ => return Err(format!("unknown color `{value}` (use a name or #rrggbb)")),
+        })
+    }
+}
+
+impl From<Rgb> for ratatui::style::Color {
+    fn from(Rgb(r, g, b): Rgb) -> Self {
+        Self::Rgb(r, g, b)
+    }
+}
+
+impl From<Rgb> for crossterm::style::Color {
+    fn from(Rgb(r, g, b): Rgb) -> Self {
+        Self::Rgb { r, g, b }
+    }
+}
+
+impl From<Rgb> for eframe::egui::Color32 {
+    fn from(Rgb(r, g, b): Rgb) -> Self {
+        Self::from_rgb(r, g, b)
+    }
+}
+
+/// Colors for each byte class.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Palette {
+    pub null: Rgb,
+    pub full: Rgb,
+    pub whitespace: Rgb,
+    pub printable: Rgb,
+    pub control: Rgb,
+    pub high: Rgb,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            null: Rgb(0x6C, 0x6C, 0x6C),
+            full: Rgb(0xD7, 0x5F, 0x5F),
+            whitespace: Rgb(0x87, 0xD7, 0x87),
+            printable: Rgb(0x5F, 0xD7, 0xD7),
+            control: Rgb(0xD7, 0x87, 0xD7),
+            high: Rgb(0xD7, 0xD7, 0x5F),
+        }
+    }
+}
+
+impl Palette {
+    pub fn color(&self, class: ByteClass) -> Rgb {
+        match class {
+            ByteClass::Null => self.null,
+            ByteClass::Full => self.full,
+            ByteClass::Whitespace => self.whitespace,
+            ByteClass::Printable => self.printable,
+            ByteClass::Control => self.control,
+            ByteClass::High => self.high,
+        }
+    }
+
+    pub fn color_of(&self, byte: u8) -> Rgb {
+        self.color(ByteClass::of(byte))
+    }
+
+    /// Apply `overrides` from `--palette` or a theme's `[bytes]` table.
+    pub fn apply(&mut self, overrides: &PaletteOverrides) {
+        for &(class, color) in &overrides.0 {
+            *self.color_mut(class) = color;
+        }
+    }
+
+    fn color_mut(&mut self, class: ByteClass) -> &mut Rgb {
+        match class {
+            ByteClass::Null => &mut self.null,
+            ByteClass::Full => &mut self.full,
+            ByteClass::Whitespace => &mut self.whitespace,
+            ByteClass::Printable => &mut self.printable,
+            ByteClass::Control => &mut self.control,
+            ByteClass::High => &mut self.high,
+        }
+    }
+}
+
+/// Colors for some byte classes, replacing those of a theme.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PaletteOverrides(Vec<(ByteClass, Rgb)>);
+
+impl PaletteOverrides {
+    /// Parse overrides like `null=darkgray,high=#ffaf00`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut overrides = Vec::new();
+        for entry in spec
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+        {
+            let (name, color) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("expected `class=color`, got `{entry}`"))?;
+            overrides.push((parse_class(name.trim())?, color.trim().parse()?));
+        }
+        Ok(Self(overrides))
+    }
+}
+
+impl<'de> Deserialize<'de> for PaletteOverrides {
+    /// A `[bytes]` table mapping class names to colors.
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let table = std::collections::BTreeMap::<String, Rgb>::deserialize(deserializer)?;
+        table
+            .into_iter()
+            .map(|(name, color)| Ok((parse_class(&name)?, color)))
+            .collect::<Result<_, String>>()
+            .map(Self)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+fn parse_class(name: &str) -> Result<ByteClass, String> {
+    ByteClass::from_name(name).ok_or_else(|| {
+        format!("unknown byte class `{name}` (null, ff, whitespace, printable, control, high)")
+    })
+}
+
+/// Colors for everything the viewer draws.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Theme {
+    /// Whether the theme is meant for a dark background; picks the base GUI visuals.
+    pub dark: bool,
+    pub offset: Rgb,
+    pub status: Rgb,
+    pub bytes: Palette,
+    /// Background of the byte under the cursor.
+    pub cursor: Rgb,
+    /// Background of selected byte ranges.
+    pub selection: Rgb,
+    /// Background of search matches.
+    pub search_hit: Rgb,
+    pub diff_added: Rgb,
+    pub diff_removed: Rgb,
+    /// Backgrounds of template fields; neighbouring fields alternate between the two.
+    pub field: Rgb,
+    pub field_alt: Rgb,
+}
+
+/// Names of the themes that are always available.
+pub const BUILTIN_THEMES: [&str; 4] = ["dark", "light", "high-contrast", "colorblind"];
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            dark: true,
+            offset: Rgb(0x8A, 0x8A, 0x8A),
+            status: Rgb(0xA8, 0xA8, 0xA8),
+            bytes: Palette::default(),
+            cursor: Rgb(0x00, 0x5F, 0x87),
+            selection: Rgb(0x3A, 0x3A, 0x3A),
+            search_hit: Rgb(0x87, 0x5F, 0x00),
+            diff_added: Rgb(0x5F, 0xAF, 0x5F),
+            diff_removed: Rgb(0xD7, 0x5F, 0x5F),
+            field: Rgb(0x26, 0x26, 0x3A),
+            field_alt: Rgb(0x26, 0x3A, 0x26),
+        }
+    }
+}
+
+impl Theme {
+    pub fn builtin(name: &str) -> Option<Self> {
+        Some(match name {
+            "dark" | "default" => Self::default(),
+            "light" => Self {
+                dark: false,
+                offset: Rgb(0x6C, 0x6C, 0x6C),
+                status: Rgb(0x44, 0x44, 0x44),
+                bytes: Palette {
+                    null: Rgb(0xA8, 0xA8, 0xA8),
+                    full: Rgb(0xAF, 0x00, 0x00),
+                    whitespace: Rgb(0x00, 0x87, 0x00),
+                    printable: Rgb(0x00, 0x5F, 0x87),
+                    control: Rgb(0x87, 0x00, 0x87),
+                    high: Rgb(0x87, 0x5F, 0x00),
+                },
+                cursor: Rgb(0xAF, 0xD7, 0xFF),
+                selection: Rgb(0xD0, 0xD0, 0xD0),
+                search_hit: Rgb(0xFF, 0xD7, 0x5F),
+                diff_added: Rgb(0x00, 0x87, 0x00),
+                diff_removed: Rgb(0xAF, 0x00, 0x00),
+                field: Rgb(0xE4, 0xE4, 0xF8),
+                field_alt: Rgb(0xE4, 0xF8, 0xE4),
+            },
+            "high-contrast" => Self {
+                dark: true,
+                offset: Rgb(0xFF, 0xFF, 0xFF),
+                status: Rgb(0xFF, 0xFF, 0xFF),
+                bytes: Palette {
+                    null: Rgb(0x80, 0x80, 0x80),
+                    full: Rgb(0xFF, 0x00, 0x00),
+                    whitespace: Rgb(0x00, 0xFF, 0x00),
+                    printable: Rgb(0xFF, 0xFF, 0xFF),
+                    control: Rgb(0xFF, 0x00, 0xFF),
+                    high: Rgb(0xFF, 0xFF, 0x00),
+                },
+                cursor: Rgb(0x00, 0x00, 0xD7),
+                selection: Rgb(0x00, 0x5F, 0x5F),
+                search_hit: Rgb(0xAF, 0x00, 0x00),
+                diff_added: Rgb(0x00, 0xFF, 0x00),
+                diff_removed: Rgb(0xFF, 0x00, 0x00),
+                field: Rgb(0x00, 0x00, 0x5F),
+                field_alt: Rgb(0x00, 0x3A, 0x00),
+            },
+            // Okabe-Ito colors, which stay distinct with the common kinds of color
+            // blindness. Diffs use blue and orange instead of green and red.
+            "colorblind" => Self {
+                dark: true,
+                offset: Rgb(0x99, 0x99, 0x99),
+                status: Rgb(0xBB, 0xBB, 0xBB),
+                bytes: Palette {
+                    null: Rgb(0x99, 0x99, 0x99),
+                    full: Rgb(0xD5, 0x5E, 0x00),
+                    whitespace: Rgb(0x00, 0x9E, 0x73),
+                    printable: Rgb(0x56, 0xB4, 0xE9),
+                    control: Rgb(0xCC, 0x79, 0xA7),
+                    high: Rgb(0xE6, 0x9F, 0x00),
+                },
+                cursor: Rgb(0x4D, 0x4D, 0x4D),
+                selection: Rgb(0x33, 0x33, 0x33),
+                search_hit: Rgb(0x6B, 0x5F, 0x00),
+                diff_added: Rgb(0x00, 0x72, 0xB2),
+                diff_removed: Rgb(0xE6, 0x9F, 0x00),
+                field: Rgb(0x1A, 0x2E, 0x3D),
+                field_alt: Rgb(0x3D, 0x33, 0x1A),
+            },
+            _ => return None,
+        })
+    }
+
+    /// Apply the settings of a `[themes.<name>]` table on top of this theme.
+    pub fn apply(&mut self, spec: &ThemeSpec) {
+        if let Some(dark) = spec.dark {
+            self.dark = dark;
+        }
+        let colors = [
+            (&mut self.offset, spec.offset),
+            (&mut self.status, spec.status),
+            (&mut self.cursor, spec.cursor),
+            (&mut self.selection, spec.selection),
+            (&mut self.search_hit, spec.search_hit),
+            (&mut self.diff_added, spec.diff_added),
+            (&mut self.diff_removed, spec.diff_removed),
+            (&mut self.field, spec.field),
+            (&mut self.field_alt, spec.field_alt),
+        ];
+        for (slot, color) in colors {
+            if let Some(color) = color {
+                *slot = color;
+            }
+        }
+        self.bytes.apply(&spec.bytes);
+    }
+}
+
+/// A theme as written in the config file. Unset colors come from the theme it extends.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ThemeSpec {
+    /// Theme to start from (default: `dark`).
+    pub extends: Option<String>,
+    pub dark: Option<bool>,
+    pub offset: Option<Rgb>,
+    pub status: Option<Rgb>,
+    pub bytes: PaletteOverrides,
+    pub cursor: Option<Rgb>,
+    pub selection: Option<Rgb>,
+    pub search_hit: Option<Rgb>,
+    pub diff_added: Option<Rgb>,
+    pub diff_removed: Option<Rgb>,
+    pub field: Option<Rgb>,
+    pub field_alt: Option<Rgb>,
+}
+
+/// Whether the `NO_COLOR` convention asks for plain output.
+pub fn no_color_env() -> bool {
+    env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
@@ -3,106 +3,620 @@ use std::{
     time::{Duration, Instant},
 };
 
-use anyhow::Result;
+use anyhow::{Result, bail};
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEvent},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, MouseButton,
+        MouseEvent, MouseEventKind,
+    },
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
 use ratatui::{
     Frame, Terminal,
-    layout::{Constraint, Direction, Layout},
+    layout::{Constraint, Direction, Layout, Rect},
     prelude::CrosstermBackend,
-    style::{Color, Style},
-    widgets::{Block, Borders, Paragraph, Wrap},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Tabs as TabBar},
 };
 
-use crate::app::App;
+use crate::{
+    app::{App, Grouping, Split},
+    cmdline::{Command, CommandLine, Outcome},
+    keymap::{Action, KeyChord, Keymap},
+    tabs::Tabs,
+};
 
 type CrosstermTerminal = Terminal<CrosstermBackend<Stdout>>;
 
-/// Setup the TUI and drive the event loop.
-pub fn run(app: &mut App) -> Result<()> {
-    let mut terminal = setup_terminal()?;
-    let result = run_loop(&mut terminal, app);
-    restore_terminal(&mut terminal)?;
+/// Rows one notch of the mouse wheel scrolls.
+const WHEEL_ROWS: isize = 3;
+
+/// Setup the TUI and drive the event loop. With `mouse` on, the wheel scrolls and
+/// clicking or dragging selects bytes.
+pub fn run(tabs: &mut Tabs<App>, keymap: &Keymap, mouse: bool) -> Result<()> {
+    let mut terminal = setup_terminal(mouse)?;
+    let result = run_loop(&mut terminal, tabs, keymap);
+    restore_terminal(&mut terminal, mouse)?;
     result
 }
 
-fn setup_terminal() -> Result<CrosstermTerminal> {
+fn setup_terminal(mouse: bool) -> Result<CrosstermTerminal> {
     enable_raw_mode()?;
     let mut stdout = stdout();
     execute!(stdout, EnterAlternateScreen)?;
+    if mouse {
+        execute!(stdout, EnableMouseCapture)?;
+    }
     let backend = CrosstermBackend::new(stdout);
     let terminal = Terminal::new(backend)?;
     Ok(terminal)
 }
 
-fn restore_terminal(terminal: &mut CrosstermTerminal) -> Result<()> {
+fn restore_terminal(terminal: &mut CrosstermTerminal, mouse: bool) -> Result<()> {
     disable_raw_mode()?;
+    if mouse {
+        execute!(terminal.backend_mut(), DisableMouseCapture)?;
+    }
     execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
     terminal.show_cursor()?;
     Ok(())
 }
 
-fn run_loop(terminal: &mut CrosstermTerminal, app: &mut App) -> Result<()> {
+/// Keys typed so far towards a binding of more than one key, and the `:` command line.
+#[derive(Default)]
+struct KeyState {
+    command_line: CommandLine,
+    pending: Vec<KeyChord>,
+    /// Row and cursor the active tab had before a pending key ran its own action (like
+    /// `g` jumping to the start), so a longer binding such as `gt` can undo the jump.
+    view_before: Option<(usize, usize)>,
+    /// First line shown in the key binding help, while it is open.
+    help: Option<usize>,
+}
+
+fn run_loop(terminal: &mut CrosstermTerminal, tabs: &mut Tabs<App>, keymap: &Keymap) -> Result<()> {
     let tick_rate = Duration::from_millis(250);
     let mut last_tick = Instant::now();
+    let mut keys = KeyState::default();
+    let mut mouse = MouseState::default();
     loop {
-        terminal.draw(|f| draw_ui(f, app))?;
+        terminal.draw(|f| draw_ui(f, tabs, keymap, &mut keys, &mut mouse))?;
 
         let timeout = tick_rate
             .checked_sub(last_tick.elapsed())
             .unwrap_or(Duration::from_millis(0));
 
         if event::poll(timeout)? {
-            if let Event::Key(key) = event::read()? {
-                if !handle_key(app, key) {
-                    break;
-                }
+            match event::read()? {
+                Event::Key(key) if !handle_key(tabs, keymap, &mut keys, key) => break,
+                Event::Mouse(event) => handle_mouse(tabs.active_mut(), &mut mouse, event),
+                _ => {}
             }
         }
 
         if last_tick.elapsed() >= tick_rate {
+            for app in tabs.iter_mut() {
+                app.poll_incoming();
+            }
             last_tick = Instant::now();
         }
     }
     Ok(())
 }
 
-fn draw_ui(frame: &mut Frame, app: &mut App) {
-    let chunks = Layout::default()
+/// Where the panes were drawn, and the byte a mouse drag started on.
+#[derive(Default)]
+struct MouseState {
+    /// Area inside each pane's border, by pane index.
+    panes: Vec<Rect>,
+    anchor: Option<usize>,
+    fields: FieldPanel,
+}
+
+/// The template field list beside the panes.
+#[derive(Default)]
+struct FieldPanel {
+    /// Area inside the border, or `None` while no template is applied.
+    area: Option<Rect>,
+    /// First line shown.
+    scroll: usize,
+    /// Line of the field under the cursor when last drawn; the list only follows the
+    /// cursor when it moves to another field, so the wheel can scroll it freely.
+    current: Option<usize>,
+}
+
+/// Draw the screen, recording where the panes and field list went for mouse handling.
+fn draw_ui(
+    frame: &mut Frame,
+    tabs: &mut Tabs<App>,
+    keymap: &Keymap,
+    keys: &mut KeyState,
+    mouse: &mut MouseState,
+) {
+    let command_line = &keys.command_line;
+    // The tab bar is only shown when more than one file is open.
+    let tab_bar_height = if tabs.len() > 1 { 1 } else { 0 };
+    let status_height = if tabs.active().is_status_bar_visible()
+        || command_line.input().is_some()
+        || command_line.error().is_some()
+    {
+        1
+    } else {
+        0
+    };
+    let layout = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Min(1), Constraint::Length(1)])
+        .constraints([
+            Constraint::Length(tab_bar_height),
+            Constraint::Min(1),
+            Constraint::Length(status_height),
+        ])
         .split(frame.area());
+    let chunks = &layout[1..];
 
-    let visible_rows = chunks[0].height.max(1) as usize;
-    app.set_view_rows(visible_rows);
+    if tabs.len() > 1 {
+        let titles: Vec<String> = tabs
+            .iter()
+            .enumerate()
+            .map(|(idx, app)| format!("{}:{}", idx + 1, app.title()))
+            .collect();
+        let bar = TabBar::new(titles)
+            .select(tabs.active_index())
+            .style(status_style(tabs.active()))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_widget(bar, layout[0]);
+    }
+
+    let app = tabs.active_mut();
+
+    let mut main_area = chunks[0];
+    mouse.fields.area = None;
+    if app.template_fields().is_some() {
+        let width = (main_area.width / 3).clamp(24, 48).min(main_area.width / 2);
+        let [panes, fields] =
+            Layout::horizontal([Constraint::Min(1), Constraint::Length(width)]).areas(main_area);
+        main_area = panes;
+        draw_fields(frame, app, fields, &mut mouse.fields);
+    }
+
+    let direction = match app.split() {
+        Some(Split::Vertical) => Direction::Horizontal,
+        _ => Direction::Vertical,
+    };
+    let pane_areas = Layout::default()
+        .direction(direction)
+        .constraints(vec![
+            Constraint::Ratio(1, app.pane_count() as u32);
+            app.pane_count()
+        ])
+        .split(main_area);
+
+    // Panes share one row width, so fit it to the narrowest one (minus borders).
+    let columns = pane_areas.iter().map(|area| area.width).min().unwrap_or(0);
+    let columns = columns.saturating_sub(2) as usize;
+    app.fit_bytes_per_row(|app, width| app.row_chars(width) <= columns);
+
+    for (pane, area) in pane_areas.iter().enumerate() {
+        // The bordered block takes one line above and below the rows.
+        let visible_rows = area.height.saturating_sub(2).max(1) as usize;
+        app.set_view_rows(pane, visible_rows);
+
+        let mut block = Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" {} ", app.file_name()));
+        if app.split().is_some() && pane == app.focused_pane() {
+            block = block.border_style(Style::default().fg(Color::Cyan));
+        }
+        // Rows wider than a narrow pane are cut off rather than wrapped, so each line
+        // stays one row and mouse positions still map to bytes.
+        let body = Paragraph::new(app.render_lines(pane, visible_rows)).block(block);
+
+        frame.render_widget(body, *area);
+    }
+    mouse.panes = pane_areas
+        .iter()
+        .map(|area| Block::default().borders(Borders::ALL).inner(*area))
+        .collect();
 
-    let body = Paragraph::new(app.render_lines(visible_rows))
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title(format!(" {} ", app.file_name())),
+    if command_line.input().is_some() || command_line.error().is_some() {
+        draw_command_line(frame, app, command_line, chunks[1]);
+    } else {
+        let quit_keys = keymap.keys_for(Action::Quit).map(ToString::to_string);
+        let status = Paragraph::new(app.status_line(quit_keys.as_deref())).style(status_style(app));
+        frame.render_widget(status, chunks[1]);
+    }
+    if let Some(scroll) = &mut keys.help {
+        draw_help(frame, app, keymap, scroll);
+    }
+}
+
+/// The applied template as an indented list of fields, with the innermost field under
+/// the cursor highlighted.
+fn draw_fields(frame: &mut Frame, app: &App, area: Rect, panel: &mut FieldPanel) {
+    let Some(root) = app.template_fields() else {
+        return;
+    };
+    let lines = root.lines();
+    let cursor = app.cursor();
+    // Fields come parents first, so the last one holding the cursor is the innermost.
+    let current = cursor.and_then(|cursor| {
+        lines
+            .iter()
+            .rposition(|line| line.field.range.contains(&cursor))
+    });
+    let inner = Block::default().borders(Borders::ALL).inner(area);
+    let rows = inner.height as usize;
+    if current != panel.current
+        && let Some(current) = current
+    {
+        if current < panel.scroll {
+            panel.scroll = current;
+        } else if current >= panel.scroll + rows {
+            panel.scroll = current + 1 - rows;
+        }
+    }
+    panel.current = current;
+    panel.scroll = panel.scroll.min(lines.len().saturating_sub(rows));
+    panel.area = Some(inner);
+
+    let highlight = match app.theme() {
+        Some(theme) => Style::default().bg(theme.cursor.into()),
+        None => Style::default().add_modifier(Modifier::REVERSED),
+    };
+    let text: Vec<Line> = lines
+        .iter()
+        .enumerate()
+        .skip(panel.scroll)
+        .take(rows)
+        .map(|(idx, line)| {
+            let field = line.field;
+            let mut text = format!("{}{}", "  ".repeat(line.depth), field.name);
+            if !field.value.is_empty() {
+                text.push_str(": ");
+                text.push_str(&field.value);
+            }
+            let style = if Some(idx) == current {
+                highlight
+            } else {
+                Style::default()
+            };
+            Line::styled(text, style)
+        })
+        .collect();
+    let title = format!(
+        " {} @ 0x{:X} ",
+        root.name,
+        app.display_offset(root.range.start)
+    );
+    frame.render_widget(
+        Paragraph::new(text).block(Block::default().borders(Borders::ALL).title(title)),
+        area,
+    );
+}
+
+/// Clicking a field in the template list selects its bytes; the wheel scrolls the list.
+fn handle_field_mouse(app: &mut App, panel: &mut FieldPanel, area: Rect, event: MouseEvent) {
+    let Some(root) = app.template_fields() else {
+        return;
+    };
+    let lines = root.lines();
+    match event.kind {
+        MouseEventKind::ScrollDown => {
+            let max_scroll = lines.len().saturating_sub(area.height as usize);
+            panel.scroll = (panel.scroll + WHEEL_ROWS as usize).min(max_scroll);
+        }
+        MouseEventKind::ScrollUp => {
+            panel.scroll = panel.scroll.saturating_sub(WHEEL_ROWS as usize);
+        }
+        MouseEventKind::Down(MouseButton::Left) => {
+            let line = panel.scroll + (event.row - area.y) as usize;
+            if let Some(line) = lines.get(line) {
+                let range = line.field.range.clone();
+                app.select_field(range);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// A popup listing every key binding, scrolled down `scroll` lines.
+fn draw_help(frame: &mut Frame, app: &App, keymap: &Keymap, scroll: &mut usize) {
+    let entries = keymap.help();
+    let keys_width = entries
+        .iter()
+        .map(|(keys, ..)| keys.chars().count())
+        .max()
+        .unwrap_or(0);
+    let lines: Vec<Line> = entries
+        .into_iter()
+        .map(|(keys, _, description)| {
+            Line::from(vec![
+                Span::styled(format!("{keys:<keys_width$}  "), status_style(app)),
+                Span::raw(description),
+            ])
+        })
+        .collect();
+
+    let screen = frame.area();
+    let width = (keys_width as u16 + 44).min(screen.width.saturating_sub(4));
+    let height = (lines.len() as u16 + 2).min(screen.height.saturating_sub(2));
+    let area = Rect::new(
+        screen.x + (screen.width - width) / 2,
+        screen.y + (screen.height - height) / 2,
+        width,
+        height,
+    );
+    let max_scroll = lines
+        .len()
+        .saturating_sub(height.saturating_sub(2) as usize);
+    *scroll = (*scroll).min(max_scroll);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(" Key bindings ")
+        .title_bottom(" j/k scroll, any other key closes ");
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(lines)
+            .block(block)
+            .scroll((*scroll as u16, 0)),
+        area,
+    );
+}
+
+/// Scroll with the wheel, select a byte with a click and a range by dragging. The
+/// pane under the mouse gets the focus.
+fn handle_mouse(app: &mut App, mouse: &mut MouseState, event: MouseEvent) {
+    if let Some(area) = mouse.fields.area
+        && !matches!(event.kind, MouseEventKind::Drag(_))
+        && contains(area, &event)
+    {
+        mouse.anchor = None;
+        return handle_field_mouse(app, &mut mouse.fields, area, event);
+    }
+    let Some(pane) = mouse.panes.iter().position(|area| contains(*area, &event)) else {
+        if !matches!(event.kind, MouseEventKind::Drag(MouseButton::Left)) {
+            mouse.anchor = None;
+        }
+        return drag_outside(app, mouse, event);
+    };
+    let area = mouse.panes[pane];
+    let index_at = |app: &App, event: &MouseEvent| {
+        app.index_at(
+            pane,
+            (event.row - area.y) as usize,
+            (event.column - area.x) as usize,
         )
-        .wrap(Wrap { trim: false });
+    };
+    match event.kind {
+        MouseEventKind::ScrollDown => {
+            app.focus_pane(pane);
+            app.scroll_rows(WHEEL_ROWS);
+        }
+        MouseEventKind::ScrollUp => {
+            app.focus_pane(pane);
+            app.scroll_rows(-WHEEL_ROWS);
+        }
+        MouseEventKind::Down(MouseButton::Left) => {
+            app.focus_pane(pane);
+            mouse.anchor = index_at(app, &event);
+            match mouse.anchor {
+                Some(index) => {
+                    app.set_cursor(index);
+                    app.select(index, index);
+                }
+                None => app.clear_selection(),
+            }
+        }
+        MouseEventKind::Drag(MouseButton::Left) if app.focused_pane() == pane => {
+            if let (Some(anchor), Some(index)) = (mouse.anchor, index_at(app, &event)) {
+                app.set_cursor(index);
+                app.select(anchor, index);
+            }
+        }
+        _ => {}
+    }
+}
 
-    frame.render_widget(body, chunks[0]);
+fn contains(area: Rect, event: &MouseEvent) -> bool {
+    (area.left()..area.right()).contains(&event.column)
+        && (area.top()..area.bottom()).contains(&event.row)
+}
 
-    let status = Paragraph::new(app.status_line()).style(Style::default().fg(Color::Gray));
-    frame.render_widget(status, chunks[1]);
+/// Dragging above or below the focused pane scrolls it and extends the selection to the
+/// row that comes into view.
+fn drag_outside(app: &mut App, mouse: &MouseState, event: MouseEvent) {
+    let (Some(anchor), MouseEventKind::Drag(MouseButton::Left)) = (mouse.anchor, event.kind) else {
+        return;
+    };
+    let Some(area) = mouse.panes.get(app.focused_pane()) else {
+        return;
+    };
+    let stride = app.bytes_per_row();
+    let last = app.bytes_len().saturating_sub(1);
+    let index = if event.row < area.top() {
+        app.scroll_rows(-1);
+        app.scroll_row() * stride
+    } else if event.row >= area.bottom() {
+        app.scroll_rows(1);
+        let bottom = app.scroll_row() + app.view_rows();
+        (bottom * stride).saturating_sub(1).min(last)
+    } else {
+        return;
+    };
+    app.set_cursor(index);
+    app.select(anchor, index);
 }
 
-fn handle_key(app: &mut App, key: KeyEvent) -> bool {
+/// The `:` prompt with any completions after it, or the last command's error.
+fn draw_command_line(frame: &mut Frame, app: &App, command_line: &CommandLine, area: Rect) {
+    let Some(input) = command_line.input() else {
+        let error = command_line.error().unwrap_or_default().to_string();
+        let color = app
+            .theme()
+            .map_or(Color::Red, |theme| theme.diff_removed.into());
+        frame.render_widget(
+            Paragraph::new(error).style(Style::default().fg(color)),
+            area,
+        );
+        return;
+    };
+    let mut spans = vec![Span::raw(format!(":{input}"))];
+    if let Some(completions) = command_line.completions() {
+        spans.push(Span::styled(format!("   {completions}"), status_style(app)));
+    }
+    frame.render_widget(Paragraph::new(Line::from(spans)), area);
+    let x = area.x + 1 + input.chars().count() as u16;
+    frame.set_cursor_position((x.min(area.right().saturating_sub(1)), area.y));
+}
+
+fn status_style(app: &App) -> Style {
+    let color = app.theme().map_or(Color::Gray, |theme| theme.status.into());
+    Style::default().fg(color)
+}
+
+fn handle_key(tabs: &mut Tabs<App>, keymap: &Keymap, keys: &mut KeyState, key: KeyEvent) -> bool {
+    keys.command_line.set_error(None);
+    if let Some(scroll) = &mut keys.help {
+        match key.code {
+            KeyCode::Down | KeyCode::Char('j') => *scroll += 1,
+            KeyCode::Up | KeyCode::Char('k') => *scroll = scroll.saturating_sub(1),
+            KeyCode::PageDown | KeyCode::Char(' ') => *scroll += 10,
+            KeyCode::PageUp => *scroll = scroll.saturating_sub(10),
+            _ => keys.help = None,
+        }
+        return true;
+    }
+    if keys.command_line.input().is_some() {
+        return handle_command_key(tabs, keys, key);
+    }
+    let (lookup, len) = keymap.press(&mut keys.pending, KeyChord::from(key));
+    let view_before = keys.view_before.take();
+    if !lookup.is_prefix && !lookup.action.is_some_and(Action::keeps_confirm) {
+        tabs.active_mut().cancel_confirm();
+    }
+
+    let mut keep_going = true;
+    if let Some(action) = lookup.action {
+        if len > 1
+            && let Some((row, cursor)) = view_before
+        {
+            let app = tabs.active_mut();
+            app.scroll_to_row(row);
+            app.set_cursor(cursor);
+        }
+        let app = tabs.active();
+        let view = (app.scroll_row(), app.cursor().unwrap_or(0));
+        match action {
+            Action::CommandLine => keys.command_line.open(),
+            Action::Help => keys.help = Some(0),
+            _ => keep_going = run_action(tabs, action),
+        }
+        if lookup.is_prefix {
+            keys.view_before = Some(view);
+        }
+    }
+    keep_going
+}
+
+/// Edit the command line, running it on Enter; returns `false` to quit.
+fn handle_command_key(tabs: &mut Tabs<App>, keys: &mut KeyState, key: KeyEvent) -> bool {
+    let command_line = &mut keys.command_line;
     match key.code {
-        KeyCode::Char('q') | KeyCode::Esc => return false,
-        KeyCode::Down | KeyCode::Char('j') => app.scroll_rows(1),
-        KeyCode::Up | KeyCode::Char('k') => app.scroll_rows(-1),
-        KeyCode::PageDown | KeyCode::Char(' ') => app.scroll_rows(app.view_rows() as isize),
-        KeyCode::PageUp => app.scroll_rows(-(app.view_rows() as isize)),
-        KeyCode::Home | KeyCode::Char('g') => app.scroll_to_start(),
-        KeyCode::End | KeyCode::Char('G') => app.scroll_to_end(),
+        KeyCode::Esc => command_line.close(),
+        KeyCode::Backspace => command_line.backspace(),
+        KeyCode::Tab => command_line.complete(),
+        KeyCode::Up => command_line.recall(true),
+        KeyCode::Down => command_line.recall(false),
+        KeyCode::Enter => {
+            let line = command_line.submit().unwrap_or_default();
+            if line.trim().is_empty() {
+                return true;
+            }
+            match run_command(tabs, &mut keys.help, &line) {
+                Ok(keep_going) => return keep_going,
+                Err(err) => command_line.set_error(Some(format!("{err:#}"))),
+            }
+        }
+        KeyCode::Char(c) => command_line.push(c),
+        _ => {}
+    }
+    true
+}
+
+/// Run a command line, opening the help popup in `help` if asked; returns `false` to quit.
+fn run_command(tabs: &mut Tabs<App>, help: &mut Option<usize>, line: &str) -> Result<bool> {
+    let outcome = Command::parse(line)?.run(tabs.active_mut())?;
+    Ok(match outcome {
+        Outcome::Done | Outcome::Goto(_) => true,
+        Outcome::Quit { force } => {
+            if !force && tabs.iter().any(App::is_modified) {
+                bail!("No write since last change (add ! to override)");
+            }
+            false
+        }
+        Outcome::Action(Action::CommandLine) => true,
+        Outcome::Action(Action::Help) => {
+            *help = Some(0);
+            true
+        }
+        Outcome::Action(action) => run_action(tabs, action),
+    })
+}
+
+/// Carry out `action` on the active tab; returns `false` to quit.
+fn run_action(tabs: &mut Tabs<App>, action: Action) -> bool {
+    match action {
+        Action::Quit if !tabs.iter().any(App::is_modified) => return false,
+        Action::Quit => tabs
+            .active_mut()
+            .set_message("No write since last change (:q! quits without saving)".to_string()),
+        Action::NextTab => tabs.cycle(1),
+        Action::PrevTab => tabs.cycle(-1),
+        Action::SelectTab(index) => tabs.select(index),
         _ => {}
     }
+    let app = tabs.active_mut();
+    match action {
+        Action::CursorLeft => app.move_cursor(-1),
+        Action::CursorRight => app.move_cursor(1),
+        Action::CursorDown => app.move_cursor(app.bytes_per_row() as isize),
+        Action::CursorUp => app.move_cursor(-(app.bytes_per_row() as isize)),
+        Action::ScrollDown => app.scroll_rows(1),
+        Action::ScrollUp => app.scroll_rows(-1),
+        Action::PageDown => app.scroll_rows(app.view_rows() as isize),
+        Action::PageUp => app.scroll_rows(-(app.view_rows() as isize)),
+        Action::GotoStart => app.scroll_to_start(),
+        Action::GotoEnd => app.scroll_to_end(),
+        Action::Save => {
+            if let Err(err) = app.save() {
+                app.set_message(format!("save failed: {err:#}"));
+            }
+        }
+        Action::Open => app.set_message("opening files needs the GUI (--gui)".to_string()),
+        Action::ToggleEdit => app.set_message("typing bytes needs the GUI (--gui)".to_string()),
+        Action::Reload => app.request_reload(),
+        Action::SplitHorizontal => app.toggle_split(Split::Horizontal),
+        Action::SplitVertical => app.toggle_split(Split::Vertical),
+        Action::NextPane => app.focus_next_pane(),
+        Action::ToggleScrollLock => app.toggle_scroll_lock(),
+        Action::ToggleStatusBar => app.toggle_status_bar(),
+        Action::CycleBase => app.set_base(app.base().next()),
+        Action::CycleGroup => app.set_grouping(app.grouping().next_size()),
+        Action::ToggleLittleEndian => {
+            let grouping = app.grouping();
+            app.set_grouping(Grouping {
+                little_endian: !grouping.little_endian,
+                ..grouping
+            });
+        }
+        Action::Quit
+        | Action::CommandLine
+        | Action::Help
+        | Action::NextTab
+        | Action::PrevTab
+        | Action::SelectTab(_) => {}
+    }
     true
 }